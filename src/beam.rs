@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::bullet::PLAYER_BULLET_LAYERS;
+use crate::collider::Collider;
+use crate::enemy::{Damage, DamageSet};
+use crate::input::InputState;
+use crate::physics::{PhysicsSet, Position, Raycast, Rotation};
+use crate::spaceship::{firing_direction, Spaceship};
+use crate::weapon::{Weapon, MUZZLE_OFFSET};
+
+pub struct BeamPlugin;
+
+impl Plugin for BeamPlugin {
+    fn build(&self, app: &mut App) {
+        // against where everything ended up this tick
+        app.add_systems(FixedUpdate, fire_beams.after(PhysicsSet).before(DamageSet));
+    }
+}
+
+/// A hitscan weapon: a ray from the ship's nose, cast every tick while the trigger is held.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Beam {
+    pub range: f32,
+    /// Of the sprite only; the ray itself has no width.
+    pub width: f32,
+    /// Whether the beam goes through everything in range instead of stopping at the first hit.
+    pub piercing: bool,
+}
+
+/// The sprite showing a ship's beam, a child of the ship. Hidden while the ship isn't firing one.
+#[derive(Component)]
+pub struct BeamSprite;
+
+/// Damages whatever the beams touch, by the weapon's damage per second, and stretches each beam's
+/// sprite from the nose to where the beam stops.
+fn fire_beams(
+    q_spaceship: Query<(&Position, &Rotation, &Weapon, &InputState, &Children), With<Spaceship>>,
+    // beam sprites have neither, which keeps them apart from what the rays are cast against
+    mut q_sprites: Query<
+        (&mut Transform, &mut Sprite, &mut Handle<Image>, &mut Visibility),
+        (With<BeamSprite>, Without<Position>, Without<Collider>)
+    >,
+    raycast: Raycast,
+    mut e_damage: EventWriter<Damage>,
+    time: Res<Time>
+) {
+    for (position, rotation, weapon, input_state, children) in q_spaceship.iter() {
+        let Some(&child) = children.iter().find(|child| q_sprites.contains(**child)) else { continue };
+        let Ok((mut transform, mut sprite, mut texture, mut visibility)) = q_sprites.get_mut(child) else { continue };
+        let Some(beam) = weapon.beam.filter(|_| input_state.shooting) else {
+            *visibility = Visibility::Hidden;
+            continue;
+        };
+        let facing = Vec2::from_angle(rotation.current).rotate(Vec2::Y);
+        let direction = firing_direction(position.current, input_state.aim, facing);
+        let muzzle = position.current + direction * MUZZLE_OFFSET;
+        let hits: Vec<_> = if beam.piercing {
+            raycast.cast(muzzle, direction, beam.range, PLAYER_BULLET_LAYERS)
+        } else {
+            raycast.first(muzzle, direction, beam.range, PLAYER_BULLET_LAYERS).into_iter().collect()
+        };
+        for (target, _) in &hits {
+            e_damage.send(Damage(*target, weapon.damage * time.delta_seconds()));
+        }
+        let length = match hits.first() {
+            Some((_, hit)) if !beam.piercing => hit.distance,
+            _ => beam.range,
+        };
+
+        // in the ship's frame, as the sprite turns and moves with it
+        let to_local = Vec2::from_angle(-rotation.current);
+        transform.translation = (to_local.rotate(direction) * MUZZLE_OFFSET).extend(transform.translation.z);
+        transform.rotation = Quat::from_rotation_z(Vec2::Y.angle_between(to_local.rotate(direction)));
+        sprite.custom_size = Some(Vec2::new(beam.width, length));
+        if *texture != weapon.texture {
+            *texture = weapon.texture.clone();
+        }
+        *visibility = Visibility::Inherited;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use crate::collider::{CollisionLayers, Layers};
+    use crate::enemy::Enemy;
+    use crate::physics::PhysicsPlugin;
+    use super::*;
+
+    /// A ship at the origin firing up, with two enemies in line ahead of it and one off to the side.
+    fn app(piercing: bool) -> (App, Entity, [Entity; 2]) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PhysicsPlugin::default(), BeamPlugin))
+            .add_event::<Damage>();
+        let beam = Beam { range: 300., width: 4., piercing };
+        let sprite = app.world.spawn((SpriteBundle::default(), BeamSprite)).id();
+        app.world.spawn((
+            Spaceship,
+            Position { current: Vec2::ZERO, previous: Vec2::ZERO },
+            Rotation::default(),
+            Weapon { damage: 320., beam: Some(beam), ..default() },
+            InputState { shooting: true, ..default() },
+        )).add_child(sprite);
+        let [near, far, _] = [Vec2::new(0., 100.), Vec2::new(0., 200.), Vec2::new(50., 100.)].map(|position| {
+            app.world.spawn((
+                TransformBundle::default(),
+                Position { current: position, previous: position },
+                Collider::Circle(Circle::new(16.)),
+                CollisionLayers::new(Layers::ENEMY, Layers::PLAYER_PROJECTILE),
+                Enemy,
+            )).id()
+        });
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.update();
+        (app, sprite, [near, far])
+    }
+
+    fn damage(app: &mut App) -> Vec<(Entity, f32)> {
+        app.world.resource_mut::<Events<Damage>>().drain().map(|Damage(entity, damage)| (entity, damage)).collect()
+    }
+
+    #[test]
+    fn beam_stops_at_the_first_enemy_and_deals_damage_per_second() {
+        let (mut app, sprite, [near, _]) = app(false);
+        // a 64th of 320 per second
+        assert_eq!(damage(&mut app), vec![(near, 5.)]);
+        // from the nose to the near edge of the enemy
+        let size = app.world.get::<Sprite>(sprite).unwrap().custom_size.unwrap();
+        assert!(size.abs_diff_eq(Vec2::new(4., 100. - 16. - MUZZLE_OFFSET), 1e-3), "{size}");
+        assert_eq!(app.world.get::<Visibility>(sprite), Some(&Visibility::Inherited));
+    }
+
+    #[test]
+    fn piercing_beam_hits_everything_in_range() {
+        let (mut app, sprite, [near, far]) = app(true);
+        assert_eq!(damage(&mut app), vec![(near, 5.), (far, 5.)]);
+        assert_eq!(app.world.get::<Sprite>(sprite).unwrap().custom_size, Some(Vec2::new(4., 300.)));
+    }
+}
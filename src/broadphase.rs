@@ -0,0 +1,144 @@
+use bevy::prelude::*;
+use bevy::utils::HashMap;
+
+/// Side length of a spatial hash cell. Roughly twice the size of an enemy,
+/// so most colliders touch one to four cells.
+pub const CELL_SIZE: f32 = 64.;
+
+/// Uniform grid over the world, rebuilt every fixed tick. Each collider is listed in every cell its
+/// bounding box touches, so a query only has to look at the cells around the queried region.
+#[derive(Component)]
+pub struct SpatialHash {
+    cell_size: f32,
+    cells: HashMap<IVec2, Vec<Entity>>,
+}
+
+impl SpatialHash {
+    pub fn new(cell_size: f32) -> Self {
+        SpatialHash { cell_size, cells: HashMap::default() }
+    }
+
+    /// Empties every cell. Cells that were in use keep their allocation for the next rebuild.
+    pub fn clear(&mut self) {
+        self.cells.retain(|_, entities| {
+            let was_used = !entities.is_empty();
+            entities.clear();
+            was_used
+        });
+    }
+
+    pub fn insert(&mut self, entity: Entity, min: Vec2, max: Vec2) {
+        let (min_cell, max_cell) = (self.cell(min), self.cell(max));
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                self.cells.entry(IVec2::new(x, y)).or_default().push(entity);
+            }
+        }
+    }
+
+    /// Entities whose bounding boxes may overlap the box from `min` to `max`, each listed once.
+    pub fn query(&self, min: Vec2, max: Vec2) -> Vec<Entity> {
+        let (min_cell, max_cell) = (self.cell(min), self.cell(max));
+        let mut entities = Vec::new();
+        for x in min_cell.x..=max_cell.x {
+            for y in min_cell.y..=max_cell.y {
+                if let Some(cell) = self.cells.get(&IVec2::new(x, y)) {
+                    entities.extend_from_slice(cell);
+                }
+            }
+        }
+        entities.sort_unstable();
+        entities.dedup();
+        entities
+    }
+
+    fn cell(&self, point: Vec2) -> IVec2 {
+        (point / self.cell_size).floor().as_ivec2()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use bevy::time::TimeUpdateStrategy;
+    use rand::{Rng, SeedableRng};
+    use rand_chacha::ChaCha8Rng;
+    use crate::bullet::PLAYER_BULLET_LAYERS;
+    use crate::collider::{Collider, CollisionLayers, Layers};
+    use crate::physics::{Broadphase, CollisionEvent, PhysicsPlugin, Position, Velocity};
+    use super::*;
+
+    /// The enemy grid from `setup_enemies` and a cloud of bullets around it, run by the physics
+    /// plugin with the given broadphase.
+    fn scene(bullets: usize, broadphase: Broadphase) -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PhysicsPlugin { broadphase, ..default() }));
+        let body = |position: Vec2| (TransformBundle::default(), Position { current: position, previous: position }, Velocity(Vec2::ZERO));
+        for i in 0..100 {
+            app.world.spawn((
+                body(Vec2::new((i % 10) as f32 * 64. - 320., (i / 10) as f32 * 64. + 200.)),
+                Collider::Circle(Circle::new(16.)),
+                CollisionLayers::new(Layers::ENEMY, Layers::PLAYER | Layers::PLAYER_PROJECTILE),
+            ));
+        }
+        let mut rng = ChaCha8Rng::seed_from_u64(7);
+        for _ in 0..bullets {
+            app.world.spawn((
+                body(Vec2::new(rng.gen_range(-400.0..400.), rng.gen_range(0.0..900.))),
+                Collider::Circle(Circle::new(2.)),
+                PLAYER_BULLET_LAYERS,
+            ));
+        }
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app
+    }
+
+    fn hits(bullets: usize, broadphase: Broadphase) -> Vec<(Entity, Entity)> {
+        let mut app = scene(bullets, broadphase);
+        app.update();
+        let mut hits: Vec<_> = app.world.resource::<Events<CollisionEvent>>()
+            .iter_current_update_events()
+            .map(|CollisionEvent(a, b, _)| (*a, *b))
+            .collect();
+        hits.sort();
+        hits
+    }
+
+    #[test]
+    fn finds_the_same_hits_as_the_naive_loop() {
+        let hashed = hits(2000, Broadphase::SpatialHash);
+        assert!(!hashed.is_empty());
+        assert_eq!(hashed, hits(2000, Broadphase::Naive));
+    }
+
+    #[test]
+    fn box_query_lists_each_entity_once() {
+        let mut hash = SpatialHash::new(CELL_SIZE);
+        let (big, small) = (Entity::from_raw(1), Entity::from_raw(2));
+        hash.insert(big, Vec2::splat(-100.), Vec2::splat(100.));
+        hash.insert(small, Vec2::splat(300.), Vec2::splat(310.));
+        assert_eq!(hash.query(Vec2::splat(-10.), Vec2::splat(90.)), vec![big]);
+        assert_eq!(hash.query(Vec2::splat(-10.), Vec2::splat(305.)), vec![big, small]);
+    }
+
+    /// Run with `cargo test --release broadphase -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_broadphase_against_naive_loop() {
+        const TICKS: u32 = 2000;
+        for bullets in [100, 500, 2000] {
+            let [naive, hashed] = [Broadphase::Naive, Broadphase::SpatialHash].map(|broadphase| {
+                let mut app = scene(bullets, broadphase);
+                let start = Instant::now();
+                for _ in 0..TICKS {
+                    app.update();
+                }
+                start.elapsed() / TICKS
+            });
+            println!("100 enemies x {bullets:>4} bullets: naive {naive:>10.2?}/tick, spatial hash {hashed:>10.2?}/tick");
+        }
+    }
+}
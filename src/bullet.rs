@@ -1,37 +1,552 @@
+use std::collections::BTreeMap;
+use bevy::ecs::system::EntityCommands;
 use bevy::prelude::*;
-use crate::physics::{Position, Velocity};
+use serde::Deserialize;
+use crate::camera::{logical_view, Camera, CameraSet};
+use crate::collider::{Collider, CollisionLayers, Contact, ContinuousCollision, Layers};
+use crate::enemy::{Damage, DamageSet, Enemy};
+use crate::explosion::ExplosionEvent;
+use crate::missile::{Blast, Homing};
+use crate::physics::{CollisionEvent, PhysicsSet, Position, Rotation, Simulated, Velocity};
+use crate::weapon::fan;
 
-pub struct BulletPlugin;
+/// Player bullets hit enemies and hazards, and pass through everything else.
+pub const PLAYER_BULLET_LAYERS: CollisionLayers = CollisionLayers::new(Layers::PLAYER_PROJECTILE, Layers::ENEMY.union(Layers::HAZARD));
+
+/// Most projectiles kept around for reuse; more than this are only seen in bursts and get despawned.
+const MAX_DORMANT_PROJECTILES: usize = 2048;
+
+/// Enough for a projectile to have left the screen for good, even while the camera catches up.
+const CULL_MARGIN: f32 = 200.;
+
+pub struct BulletPlugin {
+    /// How far outside the camera's logical view projectiles go before they are taken out of play.
+    pub cull_margin: f32,
+}
+
+impl Default for BulletPlugin {
+    fn default() -> Self {
+        BulletPlugin { cull_margin: CULL_MARGIN }
+    }
+}
 
 impl Plugin for BulletPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(FixedUpdate, handle_bullets);
+        app
+            .insert_resource(CullMargin(self.cull_margin))
+            .add_systems(Startup, setup_projectile_pool)
+            .add_systems(
+                FixedUpdate,
+                (handle_bullets, handle_bullet_hits).chain().after(PhysicsSet).after(CameraSet).before(DamageSet)
+            );
     }
 }
+
 #[derive(Bundle)]
 pub struct BulletBundle {
     pub sprite: SpriteBundle,
     pub velocity: Velocity,
     pub position: Position,
+    pub rotation: Rotation,
     pub marker: Bullet,
-    pub timer: BulletTimer
+    pub timer: BulletTimer,
+    pub collider: Collider,
+    pub layers: CollisionLayers,
+    pub continuous: ContinuousCollision,
+    pub simulated: Simulated
+}
+
+/// A projectile, spent on the first thing it hits unless a [`Pierce`] or [`Ricochet`] keeps it going.
+#[derive(Component, Clone)]
+pub struct Bullet {
+    pub damage: f32,
+    /// Everything the projectile has hit, which it passes through from then on.
+    pub struck: Vec<Entity>,
 }
 
-#[derive(Component)]
-pub struct Bullet;
+impl Bullet {
+    pub fn new(damage: f32) -> Self {
+        Bullet { damage, struck: Vec::new() }
+    }
+}
 
-#[derive(Component)]
+/// Lets a projectile go on through `hits` more targets.
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+pub struct Pierce {
+    pub hits: u32,
+}
+
+/// Bounces a projectile off `bounces` more targets. Piercing is used up first.
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+pub struct Ricochet {
+    pub bounces: u32,
+}
+
+/// Fans out `projectiles` fragments over `angle` radians on every hit, each dealing `damage`. The
+/// fragments don't split again.
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+pub struct Split {
+    pub projectiles: u32,
+    pub angle: f32,
+    pub damage: f32,
+}
+
+/// Takes the projectile out of play once it runs out, if it hasn't left the screen before.
+#[derive(Component, Clone)]
 pub struct BulletTimer(pub Timer);
 
+/// See [`BulletPlugin::cull_margin`].
+#[derive(Resource, Clone, Copy, Debug)]
+pub struct CullMargin(pub f32);
+
+/// Projectiles that are out of play, kept hidden and switched off to be fired again, so sustained
+/// fire doesn't keep spawning and despawning entities.
+#[derive(Component, Clone)]
+pub struct ProjectilePool {
+    dormant: Vec<Entity>,
+    /// Dormant projectiles kept at most. Any beyond are despawned.
+    pub max_dormant: usize,
+}
+
+impl ProjectilePool {
+    pub fn new(max_dormant: usize) -> Self {
+        ProjectilePool { dormant: Vec::new(), max_dormant }
+    }
+
+    /// Puts `bullet` into play, reusing a dormant projectile when there is one.
+    pub fn fire<'a>(&mut self, commands: &'a mut Commands, bullet: BulletBundle) -> EntityCommands<'a> {
+        match self.dormant.pop() {
+            // the same components as before, so the entity stays where it is in storage
+            Some(entity) => {
+                let mut reused = commands.entity(entity);
+                reused.insert(bullet);
+                reused
+            }
+            None => commands.spawn(bullet),
+        }
+    }
+
+    /// Follows a dormant projectile that had to be respawned under a new id.
+    pub fn replace(&mut self, old: Entity, new: Entity) {
+        for entity in self.dormant.iter_mut().filter(|entity| **entity == old) {
+            *entity = new;
+        }
+    }
+
+    /// Takes a projectile out of play. Does nothing if it already is.
+    fn put_away(&mut self, commands: &mut Commands, entity: Entity, simulated: &mut Simulated, visibility: &mut Visibility) {
+        if !simulated.0 {
+            return;
+        }
+        simulated.0 = false;
+        if self.dormant.len() >= self.max_dormant {
+            commands.entity(entity).despawn();
+            return;
+        }
+        *visibility = Visibility::Hidden;
+        self.dormant.push(entity);
+    }
+}
+
+/// Gives the projectile `component`, or takes away the one it may still have from before.
+pub fn insert_or_remove<C: Component>(projectile: &mut EntityCommands, component: Option<C>) {
+    match component {
+        Some(component) => projectile.insert(component),
+        None => projectile.remove::<C>(),
+    };
+}
+
+fn setup_projectile_pool(mut commands: Commands) {
+    commands.spawn(ProjectilePool::new(MAX_DORMANT_PROJECTILES));
+}
 
+/// Takes projectiles out of play once they are well off screen or run out of time, so their number
+/// follows what is on screen rather than how long the trigger is held.
 fn handle_bullets(
-    mut q_bullets: Query<(Entity, &mut BulletTimer), With<Bullet>>,
+    mut q_bullets: Query<(Entity, &Position, &mut BulletTimer, &mut Simulated, &mut Visibility), With<Bullet>>,
+    mut q_pool: Query<&mut ProjectilePool>,
+    q_camera: Query<(&Position, &OrthographicProjection), With<Camera>>,
     mut commands: Commands,
+    cull_margin: Res<CullMargin>,
     time: Res<Time>
 ) {
-    for (entity, mut timer) in q_bullets.iter_mut() {
-        if timer.0.tick(time.delta()).just_finished() {
-            commands.entity(entity).despawn();
+    let mut pool = q_pool.single_mut();
+    // without a camera there is nothing to leave
+    let kept_space = q_camera
+        .get_single()
+        .ok()
+        .map(|(position, projection)| logical_view(position, projection))
+        .map(|view| Rect { min: view.min - cull_margin.0, max: view.max + cull_margin.0 });
+    for (entity, position, mut timer, mut simulated, mut visibility) in q_bullets.iter_mut() {
+        if !simulated.0 {
+            continue;
+        }
+        let expired = timer.0.tick(time.delta()).just_finished();
+        let off_screen = kept_space.is_some_and(|kept_space| !kept_space.contains(position.current));
+        if expired || off_screen {
+            pool.put_away(&mut commands, entity, &mut simulated, &mut visibility);
+        }
+    }
+}
+
+/// Goes through a bullet's hits in the order it met its targets. Each hit deals the bullet's damage,
+/// plus its [`Blast`] to every enemy around the point of impact, and releases its [`Split`]
+/// fragments. The bullet then pierces on, bounces off or is spent.
+fn handle_bullet_hits(
+    mut commands: Commands,
+    mut collisions: EventReader<CollisionEvent>,
+    mut e_damage: EventWriter<Damage>,
+    mut e_explosions: EventWriter<ExplosionEvent>,
+    mut q_bullets: Query<(
+        &mut Bullet,
+        &mut Position,
+        &mut Velocity,
+        &mut Rotation,
+        Option<&mut Pierce>,
+        Option<&mut Ricochet>,
+        &mut Simulated,
+        &mut Visibility
+    ), Without<Enemy>>,
+    q_payloads: Query<(Option<&Blast>, Option<&Split>, &BulletTimer, &Handle<Image>, &Collider, &CollisionLayers)>,
+    mut q_pool: Query<&mut ProjectilePool>,
+    q_enemies: Query<(Entity, &Position), With<Enemy>>
+) {
+    let mut pool = q_pool.single_mut();
+    // ordered by entity so replays send damage in the same order
+    let mut hits: BTreeMap<Entity, Vec<(Entity, Contact)>> = BTreeMap::new();
+    for collision in collisions.read() {
+        let Some((bullet, target, contact)) = collision.ordered(|entity| q_bullets.contains(entity)) else { continue };
+        hits.entry(bullet).or_default().push((target, contact));
+    }
+    for (bullet, mut hits) in hits {
+        hits.sort_by(|(a, a_contact), (b, b_contact)| a_contact.time.total_cmp(&b_contact.time).then(a.cmp(b)));
+        let Ok((mut marker, mut position, mut velocity, mut rotation, mut pierce, mut ricochet, mut simulated, mut visibility)) =
+            q_bullets.get_mut(bullet) else { continue };
+        let Ok((blast, split, timer, texture, collider, layers)) = q_payloads.get(bullet) else { continue };
+        for (target, contact) in hits {
+            if !simulated.0 || marker.struck.contains(&target) {
+                continue;
+            }
+            marker.struck.push(target);
+            e_damage.send(Damage(target, marker.damage));
+            if let Some(blast) = blast {
+                for (enemy, position) in q_enemies.iter() {
+                    if position.current.distance(contact.point) <= blast.radius {
+                        e_damage.send(Damage(enemy, blast.damage));
+                    }
+                }
+                e_explosions.send(ExplosionEvent(Position { current: contact.point, previous: contact.point }));
+            }
+
+            let pierced = pierce.as_mut().is_some_and(|pierce| spend(&mut pierce.hits));
+            // only off something it is heading into
+            let bounced = !pierced
+                && velocity.0.dot(contact.normal) > 0.
+                && ricochet.as_mut().is_some_and(|ricochet| spend(&mut ricochet.bounces));
+            if bounced {
+                // back to where it touched, heading away
+                position.current = position.previous.lerp(position.current, contact.time);
+                let incoming = velocity.0;
+                velocity.0 = incoming - 2. * incoming.dot(contact.normal) * contact.normal;
+                rotation.current = Vec2::Y.angle_between(velocity.0);
+            }
+
+            if let Some(split) = split {
+                for direction in fan(velocity.0.normalize_or_zero(), split.projectiles, split.angle) {
+                    let mut fragment = pool.fire(&mut commands, BulletBundle {
+                        sprite: SpriteBundle { texture: texture.clone(), ..default() },
+                        velocity: Velocity(direction * velocity.0.length()),
+                        position: Position { current: contact.point, previous: contact.point },
+                        rotation: Rotation::new(Vec2::Y.angle_between(direction)),
+                        marker: Bullet { damage: split.damage, struck: marker.struck.clone() },
+                        timer: timer.clone(),
+                        collider: collider.clone(),
+                        layers: *layers,
+                        continuous: ContinuousCollision,
+                        simulated: Simulated(true),
+                    });
+                    fragment.remove::<(Homing, Blast, Pierce, Ricochet, Split)>();
+                }
+            }
+
+            if !pierced && !bounced {
+                pool.put_away(&mut commands, bullet, &mut simulated, &mut visibility);
+            }
+            // its later hits were along the path it no longer takes
+            if bounced {
+                break;
+            }
+        }
+    }
+}
+
+/// Takes one off `count` if there is one left.
+fn spend(count: &mut u32) -> bool {
+    let left = *count > 0;
+    if left {
+        *count -= 1;
+    }
+    left
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::{Duration, Instant};
+    use bevy::time::TimeUpdateStrategy;
+    use crate::camera::{CameraPlugin, LOGICAL_VIEW_SIZE};
+    use crate::physics::PhysicsPlugin;
+    use crate::spaceship::Spaceship;
+    use super::*;
+
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PhysicsPlugin::default(), BulletPlugin::default()))
+            .add_event::<Damage>()
+            .add_event::<ExplosionEvent>();
+        app
+    }
+
+    fn spawn_target(app: &mut App, position: Vec2) -> Entity {
+        app.world.spawn((
+            TransformBundle::default(),
+            Position { current: position, previous: position },
+            Collider::Circle(Circle::new(16.)),
+            CollisionLayers::new(Layers::ENEMY, Layers::PLAYER_PROJECTILE),
+            Enemy,
+        )).id()
+    }
+
+    fn bullet(position: Vec2, velocity: Vec2, lifetime: f32) -> BulletBundle {
+        BulletBundle {
+            sprite: SpriteBundle::default(),
+            velocity: Velocity(velocity),
+            position: Position { current: position, previous: position },
+            rotation: Rotation::default(),
+            marker: Bullet::new(100.),
+            timer: BulletTimer(Timer::from_seconds(lifetime, TimerMode::Once)),
+            collider: Collider::Capsule(Capsule2d::new(1., 14.)),
+            layers: PLAYER_BULLET_LAYERS,
+            continuous: ContinuousCollision,
+            simulated: Simulated(true),
+        }
+    }
+
+    /// A bullet at rest at the origin.
+    fn spawn_bullet(app: &mut App) -> Entity {
+        app.world.spawn(bullet(Vec2::ZERO, Vec2::ZERO, 10.)).id()
+    }
+
+    fn tick(app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.update();
+    }
+
+    #[test]
+    fn bullet_touching_two_enemies_hits_only_once() {
+        let mut app = app();
+        spawn_target(&mut app, Vec2::new(-10., 0.));
+        spawn_target(&mut app, Vec2::new(10., 0.));
+        let bullet = spawn_bullet(&mut app);
+        tick(&mut app);
+
+        assert_eq!(app.world.resource::<Events<CollisionEvent>>().len(), 2);
+        assert_eq!(app.world.resource::<Events<Damage>>().len(), 1);
+        // parked for reuse rather than despawned
+        assert!(!app.world.get::<Simulated>(bullet).unwrap().0);
+        assert_eq!(app.world.query::<&ProjectilePool>().single(&app.world).dormant, vec![bullet]);
+    }
+
+    #[test]
+    fn blast_damages_every_enemy_in_range() {
+        let mut app = app();
+        let hit = spawn_target(&mut app, Vec2::new(10., 0.));
+        let nearby = spawn_target(&mut app, Vec2::new(60., 0.));
+        spawn_target(&mut app, Vec2::new(200., 0.));
+        let missile = spawn_bullet(&mut app);
+        app.world.entity_mut(missile).insert(Blast { radius: 80., damage: 50. });
+        tick(&mut app);
+
+        let mut damaged: Vec<(Entity, f32)> = app.world.resource_mut::<Events<Damage>>()
+            .drain()
+            .map(|Damage(entity, damage)| (entity, damage))
+            .collect();
+        damaged.sort_by_key(|(entity, _)| *entity);
+        assert_eq!(damaged, vec![(hit, 100.), (hit, 50.), (nearby, 50.)]);
+        assert_eq!(app.world.resource::<Events<ExplosionEvent>>().len(), 1);
+    }
+
+    #[test]
+    fn piercing_bullet_hits_each_enemy_once_and_carries_on() {
+        let mut app = app();
+        spawn_target(&mut app, Vec2::new(-10., 0.));
+        spawn_target(&mut app, Vec2::new(10., 0.));
+        let bullet = spawn_bullet(&mut app);
+        app.world.entity_mut(bullet).insert(Pierce { hits: 2 });
+        tick(&mut app);
+
+        assert_eq!(app.world.resource_mut::<Events<Damage>>().drain().count(), 2);
+        assert!(app.world.get::<Simulated>(bullet).unwrap().0);
+        assert_eq!(app.world.get::<Pierce>(bullet).unwrap().hits, 0);
+        // still touching both, but done with them
+        tick(&mut app);
+        assert_eq!(app.world.resource::<Events<Damage>>().len(), 0);
+    }
+
+    #[test]
+    fn ricochet_bounces_off_the_target() {
+        let mut app = app();
+        spawn_target(&mut app, Vec2::new(0., 40.));
+        let bullet = app.world.spawn((bullet(Vec2::ZERO, Vec2::new(0., 2000.), 10.), Ricochet { bounces: 1 })).id();
+        tick(&mut app);
+
+        assert_eq!(app.world.resource::<Events<Damage>>().len(), 1);
+        assert!(app.world.get::<Simulated>(bullet).unwrap().0);
+        let velocity = app.world.get::<Velocity>(bullet).unwrap().0;
+        assert!(velocity.abs_diff_eq(Vec2::new(0., -2000.), 1e-2), "{velocity}");
+        assert!(app.world.get::<Position>(bullet).unwrap().current.y < 24.);
+        assert_eq!(app.world.get::<Ricochet>(bullet).unwrap().bounces, 0);
+    }
+
+    #[test]
+    fn split_fans_out_fragments_that_spare_the_target() {
+        let mut app = app();
+        let target = spawn_target(&mut app, Vec2::new(10., 0.));
+        let bullet = app.world.spawn((
+            bullet(Vec2::ZERO, Vec2::new(0., 100.), 10.),
+            Split { projectiles: 3, angle: 1., damage: 20. },
+        )).id();
+        tick(&mut app);
+
+        assert_eq!(app.world.resource_mut::<Events<Damage>>().drain().count(), 1);
+        assert!(!app.world.get::<Simulated>(bullet).unwrap().0);
+        let fragments: Vec<(f32, Vec<Entity>, Vec2)> = app.world
+            .query_filtered::<(&Bullet, &Velocity, &Simulated), Without<Split>>()
+            .iter(&app.world)
+            .filter(|(_, _, simulated)| simulated.0)
+            .map(|(fragment, velocity, _)| (fragment.damage, fragment.struck.clone(), velocity.0))
+            .collect();
+        assert_eq!(fragments.len(), 3);
+        for (damage, struck, velocity) in fragments {
+            assert_eq!((damage, struck), (20., vec![target]));
+            assert!((velocity.length() - 100.).abs() < 1e-3);
+        }
+        tick(&mut app);
+        assert_eq!(app.world.resource::<Events<Damage>>().len(), 0);
+    }
+
+    #[test]
+    fn projectiles_well_off_screen_are_put_away() {
+        let mut app = app();
+        app.world.spawn((Camera, Position { current: Vec2::ZERO, previous: Vec2::ZERO }, OrthographicProjection::default()));
+        let (half_width, half_height) = (LOGICAL_VIEW_SIZE.x / 2., LOGICAL_VIEW_SIZE.y / 2.);
+        let on_screen = app.world.spawn(bullet(Vec2::new(0., half_height - 40.), Vec2::ZERO, 10.)).id();
+        let in_margin = app.world.spawn(bullet(Vec2::new(0., half_height + CULL_MARGIN - 1.), Vec2::ZERO, 10.)).id();
+        let gone = app.world.spawn(bullet(Vec2::new(-half_width - CULL_MARGIN - 1., 0.), Vec2::ZERO, 10.)).id();
+        tick(&mut app);
+
+        assert!(app.world.get::<Simulated>(on_screen).unwrap().0);
+        assert!(app.world.get::<Simulated>(in_margin).unwrap().0);
+        assert!(!app.world.get::<Simulated>(gone).unwrap().0);
+        assert_eq!(app.world.query::<&ProjectilePool>().single(&app.world).dormant, vec![gone]);
+    }
+
+    /// Bullets in play and the pool after `frames`, with a ship flying up and the camera in tow
+    /// while it drops a dense column of bullets behind it every tick.
+    fn culled_after(frames: impl Iterator<Item = Duration>) -> (Vec<Entity>, Vec<Entity>) {
+        let mut app = app();
+        app.add_plugins(CameraPlugin);
+        app.world.spawn((Spaceship, Position { current: Vec2::ZERO, previous: Vec2::ZERO }, Velocity(Vec2::new(0., 300.))));
+        app.add_systems(FixedUpdate, |mut commands: Commands, mut q_pool: Query<&mut ProjectilePool>| {
+            let mut pool = q_pool.single_mut();
+            for i in 0..20 {
+                pool.fire(&mut commands, bullet(Vec2::new(0., -(i as f32)), Vec2::new(0., -2000.), 10.));
+            }
+        });
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        for frame in frames {
+            app.insert_resource(TimeUpdateStrategy::ManualDuration(frame));
+            app.update();
+        }
+        let mut in_play: Vec<Entity> = app.world
+            .query::<(Entity, &Bullet, &Simulated)>()
+            .iter(&app.world)
+            .filter(|(_, _, simulated)| simulated.0)
+            .map(|(entity, _, _)| entity)
+            .collect();
+        in_play.sort();
+        (in_play, app.world.query::<&ProjectilePool>().single(&app.world).dormant.clone())
+    }
+
+    #[test]
+    fn culling_does_not_depend_on_frame_timing() {
+        let timestep = Duration::from_micros(15625);
+        // one tick per frame either way, but the uneven frames leave the camera drawn half a tick behind
+        let even = culled_after(vec![timestep; 31].into_iter());
+        let uneven = culled_after([timestep * 3 / 2, timestep / 2].into_iter().cycle().take(31));
+        assert!(!even.1.is_empty(), "nothing was culled, the test proves little");
+        assert_eq!(even, uneven);
+    }
+
+    const VOLLEY: usize = 40;
+
+    /// Sustained fire: a volley of short-lived bullets every tick, all through the pool.
+    fn app_under_fire(max_dormant: usize) -> App {
+        let mut app = app();
+        app.add_systems(FixedUpdate, |mut commands: Commands, mut q_pool: Query<&mut ProjectilePool>| {
+            let mut pool = q_pool.single_mut();
+            for i in 0..VOLLEY {
+                pool.fire(&mut commands, bullet(Vec2::new(i as f32 * 8., 0.), Vec2::new(0., 400.), 0.25));
+            }
+        });
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        app.world.query::<&mut ProjectilePool>().single_mut(&mut app.world).max_dormant = max_dormant;
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app
+    }
+
+    #[test]
+    fn sustained_fire_reuses_the_same_entities() {
+        let mut app = app_under_fire(MAX_DORMANT_PROJECTILES);
+        // long enough for the first volleys to run out
+        for _ in 0..60 {
+            app.update();
+        }
+        let entities = app.world.entities().len();
+        let bullets: Vec<Entity> = app.world.query_filtered::<Entity, With<Bullet>>().iter(&app.world).collect();
+        for _ in 0..120 {
+            app.update();
+        }
+        assert_eq!(app.world.entities().len(), entities);
+        let mut bullets_later: Vec<Entity> = app.world.query_filtered::<Entity, With<Bullet>>().iter(&app.world).collect();
+        bullets_later.sort();
+        let mut bullets = bullets;
+        bullets.sort();
+        assert_eq!(bullets, bullets_later);
+        let in_flight = app.world.query::<(&Bullet, &Simulated)>().iter(&app.world).filter(|(_, simulated)| simulated.0).count();
+        assert!(in_flight >= VOLLEY * 15, "{in_flight}");
+    }
+
+    /// Run with `cargo test --release bullet -- --ignored --nocapture`.
+    #[test]
+    #[ignore]
+    fn bench_pool_against_spawning() {
+        const TICKS: u32 = 2000;
+        for (name, max_dormant) in [("spawn and despawn", 0), ("pooled", MAX_DORMANT_PROJECTILES)] {
+            let mut app = app_under_fire(max_dormant);
+            for _ in 0..60 {
+                app.update();
+            }
+            let start = Instant::now();
+            for _ in 0..TICKS {
+                app.update();
+            }
+            println!("{name:>17}: {:>10.2?}/tick, {} entities", start.elapsed() / TICKS, app.world.entities().len());
         }
     }
 }
@@ -2,10 +2,15 @@ use bevy::app::{App, Startup};
 use bevy::prelude::*;
 use bevy::prelude::TimerMode::Repeating;
 use bevy::render::camera::ScalingMode;
-use rand::Rng;
-use crate::spaceship::Spaceship;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use crate::spaceship::{centroid, Spaceship};
 use crate::input::InputState;
-use crate::physics::Position;
+use crate::physics::{PhysicsSet, Position};
+
+/// The world the simulation treats as on screen at zoom 1: the height the camera always shows,
+/// by the width of a 21:9 window, the widest one laid out for.
+pub const LOGICAL_VIEW_SIZE: Vec2 = Vec2::new(1120., 480.);
 
 pub struct CameraPlugin;
 
@@ -13,14 +18,15 @@ impl Plugin for CameraPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_systems(Startup, spawn_camera)
-            .add_systems(FixedUpdate, (camera_follow, move_camera, update_visible_space).chain());
+            .configure_sets(FixedUpdate, CameraSet.after(PhysicsSet))
+            .add_systems(FixedUpdate, (camera_follow, move_camera, update_visible_space).chain().in_set(CameraSet));
 
     }
 }
 
 fn spawn_camera(mut commands: Commands) {
     let mut camera = Camera2dBundle::default();
-    camera.projection.scaling_mode = ScalingMode::FixedVertical(480.0);
+    camera.projection.scaling_mode = ScalingMode::FixedVertical(LOGICAL_VIEW_SIZE.y);
     commands.spawn(VisibleSpace {
         top_left: Vec2::ZERO,
         bottom_right: Vec2::ZERO,
@@ -32,9 +38,9 @@ fn spawn_camera(mut commands: Commands) {
         CameraData {
             target_scale: 0.9,
             zoom_speed: 0.75,
-            move_speed: 500.,
             shake: false,
             shake_timer: Timer::from_seconds(0.03, Repeating),
+            shake_rng: ChaCha8Rng::seed_from_u64(0),
             max_zoom: 2.0,
             min_zoom: 1.,
         }
@@ -44,35 +50,46 @@ fn spawn_camera(mut commands: Commands) {
 #[derive(Component)]
 pub struct Camera;
 
-#[derive(Component)]
+/// Moves and zooms the camera each fixed tick.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct CameraSet;
+
+#[derive(Component, Clone)]
 pub struct CameraData {
     pub target_scale: f32,
     pub zoom_speed: f32,
-    pub move_speed: f32,
     pub shake: bool,
     pub shake_timer: Timer,
+    /// Separate from the `GameRng`, so shaking the camera never shifts gameplay's random draws.
+    pub shake_rng: ChaCha8Rng,
     pub max_zoom: f32,
     pub min_zoom: f32
 }
 
-#[derive(Component, Debug)]
+/// What the window shows, for drawing. Follows the interpolated camera and the window's shape, so
+/// the simulation goes by [`logical_view`] instead.
+#[derive(Component, Clone, Debug)]
 pub struct VisibleSpace {
     pub top_left: Vec2,
     pub bottom_right: Vec2,
 }
 
+/// The part of the world the simulation treats as on screen, from where the camera is this tick
+/// and how far it is zoomed out. Replays and rollback agree on it whatever the window.
+pub fn logical_view(position: &Position, projection: &OrthographicProjection) -> Rect {
+    Rect::from_center_size(position.current, LOGICAL_VIEW_SIZE * projection.scale)
+}
 
 fn move_camera(
     mut q_cam: Query<(&mut OrthographicProjection, &mut CameraData, &mut Position), With<CameraData>>,
     time: Res<Time>,
-    input_state: Query<&InputState>
+    input_states: Query<&InputState>
 ) {
     let (mut projection, mut camera_data, mut position) = q_cam.single_mut();
-    let input_state = input_state.single();
     let target_scale = camera_data.target_scale;
 
 
-    if input_state.boost {
+    if input_states.iter().any(|input_state| input_state.boost) {
         camera_data.target_scale = camera_data.max_zoom;
         camera_data.shake = true;
     } else {
@@ -88,8 +105,8 @@ fn move_camera(
     }
 
     if camera_data.shake && camera_data.shake_timer.tick(time.delta()).finished() {
-        let x = rand::thread_rng().gen_range(-7f32..7f32);
-        let y = rand::thread_rng().gen_range(-20f32..20f32);
+        let x = camera_data.shake_rng.gen_range(-7f32..7f32);
+        let y = camera_data.shake_rng.gen_range(-20f32..20f32);
         position.previous = position.current;
         position.current.x += x;
         position.current.y += y;
@@ -98,15 +115,16 @@ fn move_camera(
 
 fn camera_follow(
     mut camera_position: Query<&mut Position, With<Camera>>,
-    player_position: Query<&Position, (With<Spaceship>, Without<Camera>)>,
+    player_positions: Query<&Position, (With<Spaceship>, Without<Camera>)>,
     time: Res<Time>,
 ) {
     let mut camera_position = camera_position.single_mut();
-    let player_position = player_position.single();
+    // follow the middle of the group when several players share the screen
+    let Some(player_position) = centroid(player_positions.iter()) else { return };
     camera_position.previous = camera_position.current;
     // camera_position.current.y = camera_position.current.y + (player_position.current.y - camera_position.current.y) * 8. * time.delta_seconds();
     // camera_position.current.x = camera_position.current.x + (player_position.current.x - camera_position.current.x) * 8. * time.delta_seconds();
-    camera_position.current = camera_position.current + (player_position.current - camera_position.current) * 4. * time.delta_seconds() + Vec2::new(0.0, 5.0);
+    camera_position.current = camera_position.current + (player_position - camera_position.current) * 4. * time.delta_seconds() + Vec2::new(0.0, 5.0);
 }
 
 pub fn update_visible_space(
@@ -114,14 +132,15 @@ pub fn update_visible_space(
     mut visible_space: Query<&mut VisibleSpace>
 ) {
     let (camera_transform, camera_projection) = q_camera.single();
+    // the area is already scaled by the zoom
     let mut visible_space = visible_space.single_mut();
     let top_left = Vec2::new(
-        camera_transform.translation.x - camera_projection.area.max.x * camera_projection.scale,
-        camera_transform.translation.y + camera_projection.area.max.y * camera_projection.scale,
+        camera_transform.translation.x - camera_projection.area.max.x,
+        camera_transform.translation.y + camera_projection.area.max.y,
     );
     let bottom_right = Vec2::new(
-        camera_transform.translation.x + camera_projection.area.max.x * camera_projection.scale,
-        camera_transform.translation.y - camera_projection.area.max.y * camera_projection.scale,
+        camera_transform.translation.x + camera_projection.area.max.x,
+        camera_transform.translation.y - camera_projection.area.max.y,
     );
     // mutate component only when value is changed
     if visible_space.top_left != top_left {
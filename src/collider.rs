@@ -0,0 +1,736 @@
+use std::f32::consts::TAU;
+use std::fmt;
+use std::ops::BitOr;
+use bevy::ecs::system::SystemParam;
+use bevy::prelude::*;
+use serde::{Deserialize, Serialize};
+use crate::physics::{Position, Rotation, Simulated};
+
+/// Collision shape of an entity, in its local space.
+///
+/// A shape is centred on the entity's `Position`. A collider on a child entity that has no
+/// `Position` of its own sits at the child's `Transform` relative to its parent. Every shape
+/// except [`Collider::Aabb`] turns with the entity's `Rotation`, or with its `Transform` if it
+/// has none.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub enum Collider {
+    Circle(Circle),
+    /// Box that stays axis-aligned however the entity is turned.
+    Aabb(Rectangle),
+    /// Box that turns with the entity.
+    Obb(Rectangle),
+    /// Capsule lying along the entity's local Y axis.
+    Capsule(Capsule2d),
+    /// Convex polygon. The vertices may wind either way.
+    Polygon(ConvexPolygon),
+}
+
+/// A polygon checked to be convex with an area, so its bounds and centre are always finite.
+/// Anything else is rejected when it is built or read from an asset.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+#[serde(try_from = "BoxedPolygon", into = "BoxedPolygon")]
+pub struct ConvexPolygon(BoxedPolygon);
+
+/// Why a polygon cannot be a collider.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PolygonError {
+    /// Fewer than three points, or all of them in a line.
+    NoArea,
+    NotFinite,
+    /// Dents inward, crosses itself or repeats a point.
+    NotConvex,
+}
+
+impl fmt::Display for PolygonError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PolygonError::NoArea => write!(f, "a polygon needs three points that are not all in a line"),
+            PolygonError::NotFinite => write!(f, "polygon points must be finite"),
+            PolygonError::NotConvex => write!(f, "the polygon is not convex"),
+        }
+    }
+}
+
+impl ConvexPolygon {
+    pub fn new(vertices: impl IntoIterator<Item = Vec2>) -> Result<Self, PolygonError> {
+        let vertices: Vec<Vec2> = vertices.into_iter().collect();
+        if !vertices.iter().all(|vertex| vertex.is_finite()) {
+            return Err(PolygonError::NotFinite);
+        }
+        let area = edges(&vertices).map(|(start, end)| start.perp_dot(end)).sum::<f32>() / 2.;
+        if vertices.len() < 3 || area.abs() <= f32::EPSILON {
+            return Err(PolygonError::NoArea);
+        }
+        let sides: Vec<Vec2> = edges(&vertices).map(|(start, end)| end - start).collect();
+        if sides.contains(&Vec2::ZERO) {
+            return Err(PolygonError::NotConvex);
+        }
+        let turns = (0..sides.len()).map(|i| sides[i].angle_between(sides[(i + 1) % sides.len()]));
+        // turning the same way at every corner, and only once around: a star turns twice
+        let (mut left, mut right, mut total) = (false, false, 0.);
+        for turn in turns {
+            left |= turn > 0.;
+            right |= turn < 0.;
+            total += turn;
+        }
+        if (left && right) || (total.abs() - TAU).abs() > 1e-3 {
+            return Err(PolygonError::NotConvex);
+        }
+        Ok(ConvexPolygon(BoxedPolygon::new(vertices)))
+    }
+
+    pub fn vertices(&self) -> &[Vec2] {
+        &self.0.vertices
+    }
+}
+
+impl TryFrom<BoxedPolygon> for ConvexPolygon {
+    type Error = PolygonError;
+
+    fn try_from(polygon: BoxedPolygon) -> Result<Self, PolygonError> {
+        ConvexPolygon::new(polygon.vertices.into_vec())
+    }
+}
+
+impl From<ConvexPolygon> for BoxedPolygon {
+    fn from(polygon: ConvexPolygon) -> Self {
+        polygon.0
+    }
+}
+
+/// A set of collision layers, combined with `|`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct Layers(u32);
+
+impl Layers {
+    pub const PLAYER: Layers = Layers(1 << 0);
+    pub const PLAYER_PROJECTILE: Layers = Layers(1 << 1);
+    pub const ENEMY: Layers = Layers(1 << 2);
+    pub const ENEMY_PROJECTILE: Layers = Layers(1 << 3);
+    pub const PICKUP: Layers = Layers(1 << 4);
+    pub const HAZARD: Layers = Layers(1 << 5);
+    pub const ALL: Layers = Layers(u32::MAX);
+
+    pub const fn union(self, other: Layers) -> Layers {
+        Layers(self.0 | other.0)
+    }
+
+    pub fn intersects(self, other: Layers) -> bool {
+        self.0 & other.0 != 0
+    }
+}
+
+impl BitOr for Layers {
+    type Output = Layers;
+
+    fn bitor(self, rhs: Layers) -> Layers {
+        self.union(rhs)
+    }
+}
+
+/// The layers a collider is on and the layers it collides with. Two colliders only collide
+/// when each is on a layer the other collides with. A collider without this component is on
+/// every layer and collides with everything.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct CollisionLayers {
+    pub memberships: Layers,
+    pub filters: Layers,
+}
+
+impl CollisionLayers {
+    pub const fn new(memberships: Layers, filters: Layers) -> Self {
+        CollisionLayers { memberships, filters }
+    }
+
+    pub fn interacts(&self, other: &CollisionLayers) -> bool {
+        self.memberships.intersects(other.filters) && other.memberships.intersects(self.filters)
+    }
+}
+
+impl Default for CollisionLayers {
+    fn default() -> Self {
+        CollisionLayers::new(Layers::ALL, Layers::ALL)
+    }
+}
+
+/// Where a collider is in the world: its centre and its rotation as a unit vector.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Pose {
+    pub translation: Vec2,
+    pub rotation: Vec2,
+}
+
+impl Pose {
+    pub fn new(translation: Vec2, angle: f32) -> Self {
+        Pose { translation, rotation: Vec2::from_angle(angle) }
+    }
+
+    fn from_transform(translation: Vec2, transform: &Transform) -> Self {
+        Pose::new(translation, transform.rotation.to_euler(EulerRot::ZYX).0)
+    }
+
+    /// Pose of an entity simulated by physics. Entities without a `Rotation` keep the rotation
+    /// of their `Transform`.
+    fn from_physics(position: &Position, rotation: Option<&Rotation>, transform: &Transform) -> Self {
+        match rotation {
+            Some(rotation) => Pose::new(position.current, rotation.current),
+            None => Pose::from_transform(position.current, transform),
+        }
+    }
+
+    /// The pose of a child placed at `local` relative to this pose.
+    fn then(&self, local: Pose) -> Pose {
+        Pose {
+            translation: self.apply(local.translation),
+            rotation: self.rotation.rotate(local.rotation),
+        }
+    }
+
+    fn apply(&self, point: Vec2) -> Vec2 {
+        self.translation + self.rotation.rotate(point)
+    }
+}
+
+impl Collider {
+    /// Corners of the box the shape fills at `pose`.
+    pub fn bounds(&self, pose: Pose) -> (Vec2, Vec2) {
+        let (hull, radius) = self.hull(pose);
+        let (min, max) = hull.iter().fold(
+            (Vec2::splat(f32::INFINITY), Vec2::splat(f32::NEG_INFINITY)),
+            |(min, max), vertex| (min.min(*vertex), max.max(*vertex)),
+        );
+        (min - radius, max + radius)
+    }
+
+    /// Where the two shapes touch as they stand, or `None` if they are apart. The contact's time
+    /// is 1, the end of the tick.
+    pub fn contact(&self, pose: Pose, other: &Collider, other_pose: Pose) -> Option<Contact> {
+        let (a, a_radius) = self.hull(pose);
+        let (b, b_radius) = other.hull(other_pose);
+        let contact = match closest_points(&a, &b) {
+            Some((on_a, on_b)) => surface_contact(on_a, a_radius, on_b, b_radius),
+            None => deep_contact(&a, a_radius, &b, b_radius),
+        };
+        (contact.depth >= 0.).then_some(Contact { time: 1., ..contact })
+    }
+
+    /// First contact while this shape moves in a straight line by `motion` from `pose`, with
+    /// `other` standing still. Rotation stays fixed over the move.
+    ///
+    /// Uses conservative advancement: the gap between the shapes shrinks by at most the distance
+    /// moved, so moving by the gap can never step through the other shape.
+    pub fn sweep(&self, pose: Pose, motion: Vec2, other: &Collider, other_pose: Pose) -> Option<Contact> {
+        const TOLERANCE: f32 = 0.01;
+        const MAX_STEPS: usize = 32;
+        let distance = motion.length();
+        let (b, b_radius) = other.hull(other_pose);
+        let mut time = 0.;
+        for _ in 0..MAX_STEPS {
+            let moved = Pose { translation: pose.translation + motion * time, ..pose };
+            let (a, a_radius) = self.hull(moved);
+            let Some((on_a, on_b)) = closest_points(&a, &b) else {
+                return Some(Contact { time, ..deep_contact(&a, a_radius, &b, b_radius) });
+            };
+            let contact = surface_contact(on_a, a_radius, on_b, b_radius);
+            if contact.depth >= -TOLERANCE {
+                return Some(Contact { time, ..contact });
+            }
+            // The gap only grows from here if the shapes are already moving apart.
+            if motion.dot(contact.normal) <= 0. {
+                return None;
+            }
+            time += -contact.depth / distance;
+            if time > 1. {
+                return None;
+            }
+        }
+        None
+    }
+
+    /// Where a ray from `origin` along the unit vector `direction` first meets the shape, within
+    /// `max_distance`. A ray starting inside the shape hits it at once.
+    pub fn raycast(&self, pose: Pose, origin: Vec2, direction: Vec2, max_distance: f32) -> Option<RayHit> {
+        let (hull, radius) = self.hull(pose);
+        let inside = match closest_points(&[origin], &hull) {
+            Some((_, on_hull)) => origin.distance(on_hull) <= radius,
+            None => true,
+        };
+        if inside {
+            return Some(RayHit { distance: 0., point: origin, normal: -direction });
+        }
+        // The grown hull is the hull's edges pushed out by the radius, with round corners.
+        let corners = hull.iter().filter(|_| radius > 0.).filter_map(|vertex| {
+            let distance = ray_circle(origin, direction, *vertex, radius)?;
+            let point = origin + direction * distance;
+            Some(RayHit { distance, point, normal: (point - *vertex).normalize_or_zero() })
+        });
+        let sides = edges(&hull).flat_map(|(start, end)| {
+            let outward = (end - start).perp().normalize_or_zero();
+            [outward, -outward].map(|side| (start + side * radius, end + side * radius))
+        }).filter_map(|(start, end)| {
+            let distance = ray_segment(origin, direction, start, end)?;
+            let along = (end - start).perp().normalize_or_zero();
+            let normal = if along.dot(direction) > 0. { -along } else { along };
+            Some(RayHit { distance, point: origin + direction * distance, normal })
+        });
+        corners.chain(sides)
+            .filter(|hit| hit.distance <= max_distance)
+            .min_by(|a, b| a.distance.total_cmp(&b.distance))
+    }
+
+    /// Every shape is a convex hull of world-space points, grown by a radius. A circle is a single
+    /// point and a capsule a segment, so one distance test covers every pair of shapes.
+    fn hull(&self, pose: Pose) -> (Vec<Vec2>, f32) {
+        match self {
+            Collider::Circle(circle) => (vec![pose.translation], circle.radius),
+            Collider::Aabb(rectangle) => {
+                (box_corners(rectangle.half_size).map(|corner| pose.translation + corner).to_vec(), 0.)
+            }
+            Collider::Obb(rectangle) => {
+                (box_corners(rectangle.half_size).map(|corner| pose.apply(corner)).to_vec(), 0.)
+            }
+            Collider::Capsule(capsule) => {
+                let end = Vec2::new(0., capsule.half_length);
+                (vec![pose.apply(-end), pose.apply(end)], capsule.radius)
+            }
+            Collider::Polygon(polygon) => {
+                (polygon.vertices().iter().map(|vertex| pose.apply(*vertex)).collect(), 0.)
+            }
+        }
+    }
+}
+
+fn box_corners(half_size: Vec2) -> [Vec2; 4] {
+    [
+        Vec2::new(-half_size.x, -half_size.y),
+        Vec2::new(half_size.x, -half_size.y),
+        Vec2::new(half_size.x, half_size.y),
+        Vec2::new(-half_size.x, half_size.y),
+    ]
+}
+
+/// Where a collider touches another.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Contact {
+    /// Fraction of the tick's movement done at first touch, from 0 to 1.
+    pub time: f32,
+    pub point: Vec2,
+    /// Unit vector pointing from the first collider into the second.
+    pub normal: Vec2,
+    /// How far the second collider has to move along the normal to stop touching. Negative
+    /// while the shapes are still apart.
+    pub depth: f32,
+}
+
+impl Contact {
+    /// The same contact seen from the second collider.
+    pub fn flipped(self) -> Self {
+        Contact { normal: -self.normal, ..self }
+    }
+}
+
+/// Where a ray meets a collider.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RayHit {
+    /// From the ray's origin.
+    pub distance: f32,
+    pub point: Vec2,
+    /// Unit vector pointing out of the collider's surface.
+    pub normal: Vec2,
+}
+
+/// Distance along the ray to where it enters the circle, if it does.
+fn ray_circle(origin: Vec2, direction: Vec2, centre: Vec2, radius: f32) -> Option<f32> {
+    let offset = origin - centre;
+    let along = offset.dot(direction);
+    let discriminant = along * along - (offset.length_squared() - radius * radius);
+    let distance = -along - discriminant.sqrt();
+    (discriminant >= 0. && distance >= 0.).then_some(distance)
+}
+
+/// Distance along the ray to where it crosses the segment, if it does.
+fn ray_segment(origin: Vec2, direction: Vec2, start: Vec2, end: Vec2) -> Option<f32> {
+    let segment = end - start;
+    let denominator = direction.perp_dot(segment);
+    if denominator.abs() < f32::EPSILON {
+        return None;
+    }
+    let to_start = start - origin;
+    let distance = to_start.perp_dot(segment) / denominator;
+    let along_segment = to_start.perp_dot(direction) / denominator;
+    (distance >= 0. && (0. ..=1.).contains(&along_segment)).then_some(distance)
+}
+
+/// Contact of two grown hulls from their closest points.
+fn surface_contact(on_a: Vec2, a_radius: f32, on_b: Vec2, b_radius: f32) -> Contact {
+    let distance = on_a.distance(on_b);
+    let normal = (on_b - on_a).try_normalize().unwrap_or(Vec2::X);
+    Contact {
+        time: 0.,
+        point: (on_a + normal * a_radius).lerp(on_b - normal * b_radius, 0.5),
+        normal,
+        depth: a_radius + b_radius - distance,
+    }
+}
+
+/// Contact of two grown hulls whose hulls overlap, pushed apart along the axis of least overlap.
+fn deep_contact(a: &[Vec2], a_radius: f32, b: &[Vec2], b_radius: f32) -> Contact {
+    let mut least = (Vec2::ZERO, f32::INFINITY);
+    for axis in edges(a).chain(edges(b)).filter_map(|(start, end)| (end - start).perp().try_normalize()) {
+        let (a_min, a_max) = project(a, axis);
+        let (b_min, b_max) = project(b, axis);
+        if a_max - b_min < least.1 {
+            least = (axis, a_max - b_min);
+        }
+        if b_max - a_min < least.1 {
+            least = (-axis, b_max - a_min);
+        }
+    }
+    let (a_centre, b_centre) = (centroid(a), centroid(b));
+    if least.0 == Vec2::ZERO {
+        // both are single points on top of each other
+        least = ((b_centre - a_centre).try_normalize().unwrap_or(Vec2::X), 0.);
+    }
+    Contact { time: 0., point: a_centre.lerp(b_centre, 0.5), normal: least.0, depth: least.1 + a_radius + b_radius }
+}
+
+fn project(hull: &[Vec2], axis: Vec2) -> (f32, f32) {
+    hull.iter().fold((f32::INFINITY, f32::NEG_INFINITY), |(min, max), vertex| {
+        let along = vertex.dot(axis);
+        (min.min(along), max.max(along))
+    })
+}
+
+fn centroid(hull: &[Vec2]) -> Vec2 {
+    hull.iter().sum::<Vec2>() / hull.len() as f32
+}
+
+/// The closest pair of points of two convex hulls, `None` when they overlap.
+fn closest_points(a: &[Vec2], b: &[Vec2]) -> Option<(Vec2, Vec2)> {
+    if hulls_intersect(a, b) {
+        return None;
+    }
+    // Disjoint convex shapes are closest at a vertex of one of them.
+    let mut closest = (f32::INFINITY, Vec2::ZERO, Vec2::ZERO);
+    for vertex in a {
+        for (start, end) in edges(b) {
+            let point = closest_on_segment(*vertex, start, end);
+            let distance = vertex.distance_squared(point);
+            if distance < closest.0 {
+                closest = (distance, *vertex, point);
+            }
+        }
+    }
+    for vertex in b {
+        for (start, end) in edges(a) {
+            let point = closest_on_segment(*vertex, start, end);
+            let distance = vertex.distance_squared(point);
+            if distance < closest.0 {
+                closest = (distance, point, *vertex);
+            }
+        }
+    }
+    Some((closest.1, closest.2))
+}
+
+fn hulls_intersect(a: &[Vec2], b: &[Vec2]) -> bool {
+    a.iter().any(|vertex| hull_contains(b, *vertex))
+        || b.iter().any(|vertex| hull_contains(a, *vertex))
+        || edges(a).any(|(a_start, a_end)| edges(b).any(|(b_start, b_end)| segments_cross(a_start, a_end, b_start, b_end)))
+}
+
+/// Edges of a hull. A single point is a zero-length edge and a segment is one edge.
+fn edges(hull: &[Vec2]) -> impl Iterator<Item = (Vec2, Vec2)> + '_ {
+    let count = match hull.len() {
+        0 | 1 => hull.len(),
+        2 => 1,
+        n => n,
+    };
+    (0..count).map(move |i| (hull[i], hull[(i + 1) % hull.len()]))
+}
+
+/// Whether `point` is inside a hull with an area. Points and segments contain nothing; touching
+/// them is left to the distance test.
+fn hull_contains(hull: &[Vec2], point: Vec2) -> bool {
+    if hull.len() < 3 {
+        return false;
+    }
+    let sides = edges(hull).map(|(start, end)| (end - start).perp_dot(point - start));
+    let (mut left, mut right) = (false, false);
+    for side in sides {
+        left |= side > 0.;
+        right |= side < 0.;
+    }
+    !(left && right)
+}
+
+/// Whether two segments cross at a point inside both of them.
+fn segments_cross(a_start: Vec2, a_end: Vec2, b_start: Vec2, b_end: Vec2) -> bool {
+    let a = a_end - a_start;
+    let b = b_end - b_start;
+    let side = |direction: Vec2, origin: Vec2, point: Vec2| direction.perp_dot(point - origin).signum();
+    side(a, a_start, b_start) * side(a, a_start, b_end) < 0.
+        && side(b, b_start, a_start) * side(b, b_start, a_end) < 0.
+}
+
+fn closest_on_segment(point: Vec2, start: Vec2, end: Vec2) -> Vec2 {
+    let segment = end - start;
+    let length_squared = segment.length_squared();
+    let t = if length_squared > 0. { ((point - start).dot(segment) / length_squared).clamp(0., 1.) } else { 0. };
+    start + segment * t
+}
+
+/// Makes a collider test its whole path over each tick instead of only where it ends up, so it
+/// cannot pass through thin shapes between ticks. Meant for small, fast things like bullets.
+#[derive(Component, Clone, Default)]
+pub struct ContinuousCollision;
+
+/// A collider where it is this tick.
+#[derive(Clone, Copy)]
+pub struct PlacedCollider<'a> {
+    pub collider: &'a Collider,
+    pub pose: Pose,
+    /// How far the collider moved during the tick.
+    pub motion: Vec2,
+    pub continuous: bool,
+}
+
+impl PlacedCollider<'_> {
+    /// The box covering the collider, and for continuous colliders its whole path this tick.
+    pub fn bounds(&self) -> (Vec2, Vec2) {
+        let (min, max) = self.collider.bounds(self.pose);
+        if !self.continuous {
+            return (min, max);
+        }
+        let (start_min, start_max) = self.collider.bounds(self.start());
+        (min.min(start_min), max.max(start_max))
+    }
+
+    /// Where the two colliders touch this tick. When either collider is continuous, the contact
+    /// is the first touch along their paths, otherwise it is at the end of the tick.
+    pub fn contact(&self, other: &PlacedCollider) -> Option<Contact> {
+        if !self.continuous && !other.continuous {
+            return self.collider.contact(self.pose, other.collider, other.pose);
+        }
+        // sweep in the other collider's frame, then put the contact back where the other collider is at that time
+        let contact = self.collider.sweep(self.start(), self.motion - other.motion, other.collider, other.start())?;
+        Some(Contact { point: contact.point + other.motion * contact.time, ..contact })
+    }
+
+    fn start(&self) -> Pose {
+        Pose { translation: self.pose.translation - self.motion, ..self.pose }
+    }
+}
+
+/// Every collider together with where it is in the world.
+#[derive(SystemParam)]
+pub struct Colliders<'w, 's> {
+    colliders: Query<'w, 's, (
+        Entity,
+        &'static Collider,
+        &'static Transform,
+        Option<&'static Position>,
+        Option<&'static Rotation>,
+        Option<&'static Parent>,
+        Has<ContinuousCollision>,
+        Option<&'static Simulated>,
+    )>,
+    parents: Query<'w, 's, (&'static Position, Option<&'static Rotation>, &'static Transform)>,
+}
+
+impl<'w, 's> Colliders<'w, 's> {
+    pub fn iter(&self) -> impl Iterator<Item = (Entity, PlacedCollider<'_>)> {
+        self.colliders.iter().filter_map(|(entity, collider, transform, position, rotation, parent, continuous, simulated)| {
+            if simulated.is_some_and(|simulated| !simulated.0) {
+                return None;
+            }
+            Some((entity, self.place(collider, transform, position, rotation, parent, continuous)?))
+        })
+    }
+
+    pub fn get(&self, entity: Entity) -> Option<PlacedCollider<'_>> {
+        let (_, collider, transform, position, rotation, parent, continuous, simulated) = self.colliders.get(entity).ok()?;
+        if simulated.is_some_and(|simulated| !simulated.0) {
+            return None;
+        }
+        self.place(collider, transform, position, rotation, parent, continuous)
+    }
+
+    fn place<'a>(
+        &self,
+        collider: &'a Collider,
+        transform: &Transform,
+        position: Option<&Position>,
+        rotation: Option<&Rotation>,
+        parent: Option<&Parent>,
+        continuous: bool,
+    ) -> Option<PlacedCollider<'a>> {
+        let placed = |pose, position: &Position| PlacedCollider {
+            collider,
+            pose,
+            motion: position.current - position.previous,
+            continuous,
+        };
+        if let Some(position) = position {
+            return Some(placed(Pose::from_physics(position, rotation, transform), position));
+        }
+        let (parent_position, parent_rotation, parent_transform) = self.parents.get(parent?.get()).ok()?;
+        let parent_pose = Pose::from_physics(parent_position, parent_rotation, parent_transform);
+        let pose = parent_pose.then(Pose::from_transform(transform.translation.truncate(), transform));
+        Some(placed(pose, parent_position))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    /// One of each shape, all reaching exactly 10 to the left and right of their centre.
+    fn shapes() -> Vec<Collider> {
+        vec![
+            Collider::Circle(Circle::new(10.)),
+            Collider::Aabb(Rectangle::new(20., 6.)),
+            Collider::Obb(Rectangle::new(20., 30.)),
+            Collider::Capsule(Capsule2d::new(10., 40.)),
+            Collider::Polygon(ConvexPolygon::new([
+                Vec2::new(10., 0.),
+                Vec2::new(0., 25.),
+                Vec2::new(-10., 0.),
+                Vec2::new(0., -5.),
+            ]).unwrap()),
+        ]
+    }
+
+    #[test]
+    fn every_pair_touches_at_the_sum_of_their_extents() {
+        for a in shapes() {
+            for b in shapes() {
+                let at = |x: f32| a.contact(Pose::new(Vec2::ZERO, 0.), &b, Pose::new(Vec2::new(x, 0.), 0.)).is_some();
+                assert!(at(0.), "{a:?} does not overlap {b:?} on top of it");
+                assert!(at(19.9), "{a:?} does not overlap {b:?} nearly side by side");
+                assert!(!at(20.1), "{a:?} overlaps {b:?} just apart");
+            }
+        }
+    }
+
+    #[test]
+    fn polygons_must_be_convex_with_an_area() {
+        let square = [Vec2::new(0., 0.), Vec2::new(1., 0.), Vec2::new(1., 1.), Vec2::new(0., 1.)];
+        assert!(ConvexPolygon::new(square).is_ok());
+        assert!(ConvexPolygon::new(square.into_iter().rev()).is_ok());
+
+        assert_eq!(ConvexPolygon::new([]).unwrap_err(), PolygonError::NoArea);
+        assert_eq!(ConvexPolygon::new([Vec2::ZERO, Vec2::X]).unwrap_err(), PolygonError::NoArea);
+        assert_eq!(ConvexPolygon::new([Vec2::ZERO, Vec2::X, Vec2::X * 2.]).unwrap_err(), PolygonError::NoArea);
+        assert_eq!(ConvexPolygon::new([Vec2::ZERO, Vec2::X, Vec2::NAN]).unwrap_err(), PolygonError::NotFinite);
+        let dented = [Vec2::new(0., 0.), Vec2::new(2., 0.), Vec2::new(1., 0.5), Vec2::new(2., 2.), Vec2::new(0., 2.)];
+        assert_eq!(ConvexPolygon::new(dented).unwrap_err(), PolygonError::NotConvex);
+        let star = (0..5).map(|i| Vec2::from_angle(i as f32 * TAU * 2. / 5.).rotate(Vec2::Y));
+        assert_eq!(ConvexPolygon::new(star).unwrap_err(), PolygonError::NotConvex);
+        let repeated = [Vec2::ZERO, Vec2::X, Vec2::X, Vec2::ONE];
+        assert_eq!(ConvexPolygon::new(repeated).unwrap_err(), PolygonError::NotConvex);
+
+        // and so is every polygon read from an asset
+        assert!(ron::from_str::<Collider>("Polygon((vertices: [(0., 0.), (1., 0.), (0., 1.)]))").is_ok());
+        assert!(ron::from_str::<Collider>("Polygon((vertices: []))").is_err());
+        assert!(ron::from_str::<Collider>("Polygon((vertices: [(0., 0.), (1., 0.), (2., 0.)]))").is_err());
+    }
+
+    #[test]
+    fn small_shape_inside_a_large_one_overlaps() {
+        let large = Collider::Polygon(ConvexPolygon::new([
+            Vec2::new(-100., -100.),
+            Vec2::new(100., -100.),
+            Vec2::new(0., 100.),
+        ]).unwrap());
+        let small = Collider::Obb(Rectangle::new(4., 4.));
+        assert!(large.contact(Pose::new(Vec2::ZERO, 0.), &small, Pose::new(Vec2::new(5., 5.), 0.3)).is_some());
+    }
+
+    #[test]
+    fn only_aabb_ignores_rotation() {
+        let circle = Collider::Circle(Circle::new(1.));
+        let circle_pose = Pose::new(Vec2::new(0., 20.), 0.);
+        let turned = Pose::new(Vec2::ZERO, FRAC_PI_2);
+        // 40 wide and 4 tall; turned a quarter, the oriented shapes reach the circle above them.
+        assert!(Collider::Aabb(Rectangle::new(40., 4.)).contact(turned, &circle, circle_pose).is_none());
+        assert!(Collider::Obb(Rectangle::new(40., 4.)).contact(turned, &circle, circle_pose).is_some());
+        // The capsule lies along Y, so a quarter turn lays it flat.
+        let capsule = Collider::Capsule(Capsule2d::new(2., 40.));
+        assert!(capsule.contact(Pose::new(Vec2::ZERO, 0.), &circle, circle_pose).is_some());
+        assert!(capsule.contact(turned, &circle, circle_pose).is_none());
+    }
+
+    #[test]
+    fn sweep_finds_the_first_touch_of_a_fast_bolt() {
+        let bolt = Collider::Capsule(Capsule2d::new(1., 14.));
+        let enemy = Collider::Circle(Circle::new(16.));
+        let start = Pose::new(Vec2::new(0., -100.), 0.);
+        let motion = Vec2::new(0., 200.);
+        // both ends of the move are clear of the enemy, the bolt tunnels through without the sweep
+        assert!(bolt.contact(start, &enemy, Pose::new(Vec2::ZERO, 0.)).is_none());
+        assert!(bolt.contact(Pose::new(Vec2::new(0., 100.), 0.), &enemy, Pose::new(Vec2::ZERO, 0.)).is_none());
+
+        let contact = bolt.sweep(start, motion, &enemy, Pose::new(Vec2::ZERO, 0.)).unwrap();
+        // the bolt reaches 8 ahead of its centre and touches the circle 24 below the enemy
+        assert!((contact.time - 0.38).abs() < 1e-3, "{contact:?}");
+        assert!(contact.point.abs_diff_eq(Vec2::new(0., -16.), 0.1), "{contact:?}");
+
+        assert!(bolt.sweep(start, motion, &enemy, Pose::new(Vec2::new(18., 0.), 0.)).is_none());
+        assert!(bolt.sweep(start, -motion, &enemy, Pose::new(Vec2::ZERO, 0.)).is_none());
+    }
+
+    #[test]
+    fn sweep_of_a_touching_shape_hits_at_once() {
+        let square = Collider::Obb(Rectangle::new(10., 10.));
+        let contact = square.sweep(Pose::new(Vec2::ZERO, 0.3), Vec2::X * 50., &square, Pose::new(Vec2::new(3., 0.), 0.));
+        assert_eq!(contact.map(|contact| contact.time), Some(0.));
+    }
+
+    #[test]
+    fn ray_meets_every_shape_at_its_extent() {
+        let pose = Pose::new(Vec2::ZERO, 0.);
+        for shape in shapes() {
+            let hit = shape.raycast(pose, Vec2::new(-50., 0.), Vec2::X, 100.).unwrap();
+            assert!((hit.distance - 40.).abs() < 1e-3, "{shape:?} {hit:?}");
+            assert!(hit.point.abs_diff_eq(Vec2::new(-10., 0.), 1e-3), "{shape:?} {hit:?}");
+            assert!(shape.raycast(pose, Vec2::new(-50., 0.), Vec2::X, 39.).is_none(), "{shape:?} in reach");
+            assert!(shape.raycast(pose, Vec2::new(-50., 40.), Vec2::X, 100.).is_none(), "{shape:?} hit above");
+            assert!(shape.raycast(pose, Vec2::new(-50., 0.), -Vec2::X, 100.).is_none(), "{shape:?} hit behind");
+            assert_eq!(shape.raycast(pose, Vec2::new(2., 1.), Vec2::X, 100.).map(|hit| hit.distance), Some(0.));
+        }
+    }
+
+    #[test]
+    fn ray_hits_the_rounded_side_of_a_capsule() {
+        let capsule = Collider::Capsule(Capsule2d::new(2., 40.));
+        let hit = capsule.raycast(Pose::new(Vec2::ZERO, 0.), Vec2::new(-50., 5.), Vec2::X, 100.).unwrap();
+        assert!((hit.distance - 48.).abs() < 1e-3 && hit.normal.abs_diff_eq(-Vec2::X, 1e-5), "{hit:?}");
+        // past the end of the segment, onto the round cap
+        let hit = capsule.raycast(Pose::new(Vec2::ZERO, 0.), Vec2::new(-50., 21.), Vec2::X, 100.).unwrap();
+        assert!(((hit.point - Vec2::new(0., 20.)).length() - 2.).abs() < 1e-3, "{hit:?}");
+        assert!(hit.normal.x < 0. && hit.normal.y > 0., "{hit:?}");
+    }
+
+    #[test]
+    fn layers_must_accept_each_other() {
+        let ship = CollisionLayers::new(Layers::PLAYER, Layers::ENEMY | Layers::ENEMY_PROJECTILE);
+        let bullet = CollisionLayers::new(Layers::PLAYER_PROJECTILE, Layers::ENEMY);
+        let enemy = CollisionLayers::new(Layers::ENEMY, Layers::PLAYER | Layers::PLAYER_PROJECTILE);
+        // filters alone are not enough: the mine would hit the ship, but the ship ignores hazards
+        let mine = CollisionLayers::new(Layers::HAZARD, Layers::PLAYER);
+        assert!(bullet.interacts(&enemy) && enemy.interacts(&bullet));
+        assert!(ship.interacts(&enemy));
+        assert!(!ship.interacts(&bullet));
+        assert!(!mine.interacts(&ship));
+        assert!(CollisionLayers::default().interacts(&bullet));
+    }
+
+    #[test]
+    fn bounds_follow_rotation() {
+        let (min, max) = Collider::Capsule(Capsule2d::new(1., 14.)).bounds(Pose::new(Vec2::new(5., 0.), FRAC_PI_2));
+        assert!(min.abs_diff_eq(Vec2::new(-3., -1.), 1e-4), "{min}");
+        assert!(max.abs_diff_eq(Vec2::new(13., 1.), 1e-4), "{max}");
+    }
+}
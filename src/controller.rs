@@ -0,0 +1,236 @@
+use std::fs;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::enemy::Enemy;
+use crate::input::{InputSet, InputState, PlayerSlot};
+use crate::physics::Position;
+use crate::spaceship::Spaceship;
+
+/// Drives ships' `InputState` from sources other than local devices.
+///
+/// Every player ship keeps its [`PlayerSlot`], so recordings and snapshots see it whatever
+/// drives it. The source is the controller component it carries: none for local devices, or a
+/// [`ScriptedController`] or an [`AiPilot`]. Each controller writes the ship's `InputState` once
+/// per fixed tick, after local input is latched. Another source, such as a network peer, is a
+/// new component plus a system in [`ControllerSet`] that fills it in.
+pub struct ControllerPlugin {
+    /// Takes the player ships away from their players.
+    pub autopilot: Option<Autopilot>,
+}
+
+impl Plugin for ControllerPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(autopilot) = self.autopilot.clone() {
+            app.add_systems(PostStartup, move |commands: Commands, q_ships: Query<Entity, (With<Spaceship>, With<PlayerSlot>)>| {
+                hand_over_ships(commands, q_ships, &autopilot)
+            });
+        }
+        app
+            .configure_sets(FixedPreUpdate, ControllerSet.after(InputSet))
+            .add_systems(FixedPreUpdate, (drive_scripted_controllers, drive_ai_pilots).in_set(ControllerSet));
+    }
+}
+
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct ControllerSet;
+
+/// Who flies the player ships when nobody is playing.
+#[derive(Clone, Debug)]
+pub enum Autopilot {
+    /// An [`AiPilot`], for attract-mode demos.
+    Ai,
+    /// A looping [`ScriptedController`] read from a RON file, for soak tests.
+    Script(PathBuf),
+}
+
+impl Autopilot {
+    /// Reads `--attract` or `--script <file>` from the command line.
+    pub fn from_args() -> Option<Self> {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--attract" => return Some(Autopilot::Ai),
+                "--script" => return args.next().map(|path| Autopilot::Script(path.into())),
+                _ => {}
+            }
+        }
+        None
+    }
+}
+
+/// Plays back a fixed sequence of inputs, each held for a number of ticks.
+#[derive(Component)]
+pub struct ScriptedController {
+    pub steps: Vec<ScriptStep>,
+    /// Start over after the last step instead of going idle.
+    pub looping: bool,
+    step: usize,
+    ticks_in_step: u32,
+}
+
+/// One entry of a script file. Fields left out of `input` take their idle value.
+#[derive(Deserialize)]
+pub struct ScriptStep {
+    pub ticks: u32,
+    #[serde(default)]
+    pub input: InputState,
+}
+
+impl ScriptedController {
+    pub fn new(steps: Vec<ScriptStep>, looping: bool) -> Self {
+        ScriptedController { steps, looping, step: 0, ticks_in_step: 0 }
+    }
+
+    /// Reads a RON list of [`ScriptStep`]s.
+    pub fn load(path: &PathBuf, looping: bool) -> Result<Self, String> {
+        let contents = fs::read_to_string(path).map_err(|err| err.to_string())?;
+        let steps = ron::from_str(&contents).map_err(|err| err.to_string())?;
+        Ok(ScriptedController::new(steps, looping))
+    }
+
+    fn next_input(&mut self) -> InputState {
+        if self.looping && self.step >= self.steps.len() {
+            self.step = 0;
+        }
+        let Some(step) = self.steps.get(self.step) else { return InputState::default() };
+        let mut input = step.input.clone();
+        input.set_movement(input.movement);
+        self.ticks_in_step += 1;
+        if self.ticks_in_step >= step.ticks {
+            self.step += 1;
+            self.ticks_in_step = 0;
+        }
+        input
+    }
+}
+
+/// Flies below the nearest enemy and fires once lined up with it.
+#[derive(Component)]
+pub struct AiPilot {
+    /// How far below its target the pilot holds position.
+    pub standoff: f32,
+    /// Horizontal offset from the target within which the pilot fires.
+    pub aim_tolerance: f32,
+    /// Distance from the holding point at which the pilot uses full thrust.
+    pub full_thrust_distance: f32,
+}
+
+impl Default for AiPilot {
+    fn default() -> Self {
+        AiPilot {
+            standoff: 180.,
+            aim_tolerance: 24.,
+            full_thrust_distance: 100.,
+        }
+    }
+}
+
+fn hand_over_ships(
+    mut commands: Commands,
+    q_ships: Query<Entity, (With<Spaceship>, With<PlayerSlot>)>,
+    autopilot: &Autopilot
+) {
+    for entity in q_ships.iter() {
+        // Inserting the state up front keeps local devices from being bound to the ship.
+        let mut ship = commands.entity(entity);
+        ship.insert(InputState::default());
+        match autopilot {
+            Autopilot::Ai => {
+                ship.insert(AiPilot::default());
+            }
+            Autopilot::Script(path) => match ScriptedController::load(path, true) {
+                Ok(controller) => {
+                    ship.insert(controller);
+                }
+                Err(err) => error!("could not load script {} ({err})", path.display()),
+            },
+        }
+    }
+}
+
+fn drive_scripted_controllers(mut q_ships: Query<(&mut InputState, &mut ScriptedController)>) {
+    for (mut input_state, mut controller) in q_ships.iter_mut() {
+        *input_state = controller.next_input();
+    }
+}
+
+fn drive_ai_pilots(
+    mut q_ships: Query<(&mut InputState, &Position, &AiPilot)>,
+    q_enemies: Query<&Position, With<Enemy>>
+) {
+    for (mut input_state, position, pilot) in q_ships.iter_mut() {
+        let target = q_enemies.iter()
+            .map(|enemy| enemy.current)
+            .min_by(|a, b| a.distance_squared(position.current).total_cmp(&b.distance_squared(position.current)));
+        let Some(target) = target else {
+            *input_state = InputState::default();
+            continue;
+        };
+        let holding_point = target - Vec2::new(0., pilot.standoff);
+        input_state.set_movement((holding_point - position.current) / pilot.full_thrust_distance);
+        input_state.shooting = (target.x - position.current.x).abs() < pilot.aim_tolerance && target.y > position.current.y;
+        input_state.boost = false;
+        input_state.aim = None;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use crate::input::InputPlugin;
+    use crate::replay::{ReplayMode, ReplayPlugin};
+    use super::*;
+
+    #[test]
+    fn shipped_script_parses() {
+        assert!(ScriptedController::load(&PathBuf::from("assets/scripts/soak.ron"), true).is_ok());
+    }
+
+    #[test]
+    fn script_steps_hold_for_their_ticks() {
+        let right = InputState { movement: Vec2::X, shooting: true, ..default() };
+        let mut controller = ScriptedController::new(vec![
+            ScriptStep { ticks: 2, input: right },
+            ScriptStep { ticks: 1, input: InputState::default() },
+        ], false);
+
+        let inputs: Vec<InputState> = (0..4).map(|_| controller.next_input()).collect();
+        assert!(inputs[0].right && inputs[0].shooting && !inputs[0].idle);
+        assert!(inputs[1].right);
+        assert!(inputs[2].idle && !inputs[2].shooting);
+        assert!(inputs[3].idle);
+    }
+
+    #[test]
+    fn scripted_ships_stay_players_and_are_recorded() {
+        let recording = std::env::temp_dir().join(format!("scripted-ships-{}.ron", std::process::id()));
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            bevy::input::InputPlugin,
+            InputPlugin::default(),
+            ReplayPlugin(ReplayMode::Record(recording.clone())),
+            ControllerPlugin { autopilot: Some(Autopilot::Script("assets/scripts/soak.ron".into())) },
+        ));
+        let ship = app.world.spawn((Spaceship, PlayerSlot(0), Position { current: Vec2::ZERO, previous: Vec2::ZERO })).id();
+        // The script starts by flying up; a held key must not fight it.
+        app.world.resource_mut::<ButtonInput<KeyCode>>().press(KeyCode::KeyS);
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        for _ in 0..3 {
+            app.update();
+        }
+
+        assert_eq!(app.world.get::<PlayerSlot>(ship), Some(&PlayerSlot(0)));
+        assert_eq!(app.world.get::<InputState>(ship).unwrap().movement, Vec2::Y);
+        let contents = fs::read_to_string(&recording).unwrap();
+        fs::remove_file(&recording).unwrap();
+        let frames: Vec<Vec<InputState>> = contents.lines().skip(1).map(|line| ron::from_str(line).unwrap()).collect();
+        assert!(!frames.is_empty());
+        assert!(frames.iter().all(|frame| frame.len() == 1 && frame[0].movement == Vec2::Y));
+    }
+}
@@ -1,9 +1,10 @@
 use std::collections::HashSet;
 
 use bevy::prelude::*;
+use crate::collider::{Collider, CollisionLayers, Layers};
 use crate::explosion::ExplosionEvent;
-use crate::physics::{Position, Velocity};
-use crate::spaceship::Spaceship;
+use crate::physics::{AngularVelocity, LinearDamping, Mass, PhysicsSet, Position, RigidBody, Rotation, Velocity};
+use crate::spaceship::{centroid, Spaceship};
 
 pub struct EnemiesPlugin;
 
@@ -14,7 +15,7 @@ impl Plugin for EnemiesPlugin {
         app
             .add_event::<Damage>()
             .add_systems(Startup, setup_enemies.in_set(EnemyStartupSet))
-            .add_systems(FixedUpdate, handle_damage);
+            .add_systems(FixedUpdate, handle_damage.in_set(DamageSet).after(PhysicsSet));
     }
 }
 pub fn setup_enemies(
@@ -22,8 +23,8 @@ pub fn setup_enemies(
     asset_server: Res<AssetServer>,
     q_spaceship: Query<&Position, With<Spaceship>>
 ) {
-    let player_position = q_spaceship.single();
-    let mut spawn_pos = player_position.current + Vec2::new(ENEMIES_AMOUNT as f32 * 64. / -2., 200.);
+    let player_position = centroid(q_spaceship.iter()).unwrap_or(Vec2::ZERO);
+    let mut spawn_pos = player_position + Vec2::new(ENEMIES_AMOUNT as f32 * 64. / -2., 200.);
     for _ in 0..10 {
         for _ in 0..10 {
             commands.spawn(EnemyBundle {
@@ -33,9 +34,16 @@ pub fn setup_enemies(
                 },
                 velocity: Velocity(Vec2::ZERO),
                 position: Position { current: spawn_pos, previous: spawn_pos },
+                rotation: Rotation::default(),
+                angular_velocity: AngularVelocity::default(),
+                mass: Mass::new(2.),
+                body: RigidBody { restitution: 0.5 },
+                // settles back down after being rammed
+                damping: LinearDamping(3.),
                 marker: Enemy,
                 health: Health(100.0),
-                collider: Collider(Circle::new(16.0))
+                collider: Collider::Circle(Circle::new(16.0)),
+                layers: CollisionLayers::new(Layers::ENEMY, Layers::PLAYER | Layers::PLAYER_PROJECTILE)
             });
             spawn_pos += Vec2::new(64., 0.);
         }
@@ -69,27 +77,35 @@ pub fn handle_damage(
 }
 
 
-#[derive(Component)]
+#[derive(Component, Clone)]
 pub struct Enemy;
 
-#[derive(Component)]
-pub struct Health(f32);
+#[derive(Component, Clone)]
+pub struct Health(pub f32);
 
 #[derive(Event)]
 pub struct Damage(pub Entity, pub f32);
 
-#[derive(Component)]
-pub struct Collider(pub Circle);
-
 #[derive(Bundle)]
 struct EnemyBundle {
     pub sprite: SpriteBundle,
     pub velocity: Velocity,
     pub position: Position,
+    pub rotation: Rotation,
+    pub angular_velocity: AngularVelocity,
+    pub mass: Mass,
+    pub body: RigidBody,
+    pub damping: LinearDamping,
     pub marker: Enemy,
     pub health: Health,
-    pub collider: Collider
+    pub collider: Collider,
+    pub layers: CollisionLayers
 }
 
 #[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
 pub struct EnemyStartupSet;
+
+/// Applies [`Damage`]. Systems sending it run before this set, so damage lands on the tick it was
+/// dealt and none is left waiting between ticks.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct DamageSet;
@@ -7,7 +7,9 @@ impl Plugin for ExplosionsPlugin {
     fn build(&self, app: &mut App) {
         app
             .add_event::<ExplosionEvent>()
-            .add_systems(Update, (spawn_explosions, handle_explosions));
+            // spawned within the tick, so a floating origin shift later in the tick moves them too
+            .add_systems(FixedPostUpdate, spawn_explosions)
+            .add_systems(Update, handle_explosions);
     }
 }
 
@@ -15,7 +17,7 @@ impl Plugin for ExplosionsPlugin {
 fn spawn_explosions(
     mut commands: Commands,
     mut e_explosions: EventReader<ExplosionEvent>,
-    mut asset_server: Res<AssetServer>,
+    asset_server: Res<AssetServer>,
     mut texture_atlas_layouts: ResMut<Assets<TextureAtlasLayout>>,
 ) {
 
@@ -70,13 +72,6 @@ struct AnimationIndices {
 }
 
 
-#[derive(Bundle)]
-pub struct ExplosionBundle {
-    sprite_sheet_bundle: SpriteSheetBundle,
-    marker: Explosion,
-    timer: AnimationTimer
-}
-
 #[derive(Component)]
 pub struct Explosion;
 
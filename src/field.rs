@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use bevy::sprite::{MaterialMesh2dBundle, Mesh2dHandle};
+use crate::collider::{Collider, CollisionLayers, Layers};
+use crate::physics::{Mass, Position, RigidBody, Velocity};
+
+/// Planets, black holes and repulsor fields. Their pull is applied by the physics integrator.
+pub struct ForceFieldPlugin;
+
+impl Plugin for ForceFieldPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Startup, setup_world_objects);
+    }
+}
+
+/// Accelerates everything with a `Velocity` within `radius` of the field's `Position`, bullets
+/// included, regardless of mass. Bodies of infinite mass stay put.
+#[derive(Component, Clone, Copy, Debug)]
+pub struct ForceField {
+    pub radius: f32,
+    /// Acceleration towards the centre at full strength. Negative values push away.
+    pub strength: f32,
+    pub falloff: Falloff,
+}
+
+/// How a field weakens with distance from its centre.
+#[derive(Clone, Copy, Debug)]
+pub enum Falloff {
+    /// Fades to nothing at the edge.
+    Linear,
+    /// Gravity. Full strength at the surface of a body with the given radius, a quarter of it
+    /// at twice that distance, and so on. Never stronger than at the surface.
+    InverseSquare(f32),
+}
+
+impl ForceField {
+    /// Acceleration of something at `point` from this field centred at `centre`.
+    pub fn acceleration(&self, centre: Vec2, point: Vec2) -> Vec2 {
+        let offset = centre - point;
+        let distance = offset.length();
+        if distance > self.radius {
+            return Vec2::ZERO;
+        }
+        let scale = match self.falloff {
+            Falloff::Linear => 1. - distance / self.radius,
+            Falloff::InverseSquare(surface) => (surface / distance.max(surface)).powi(2),
+        };
+        offset.normalize_or_zero() * self.strength * scale
+    }
+}
+
+fn setup_world_objects(
+    mut commands: Commands,
+    mut meshes: ResMut<Assets<Mesh>>,
+    mut materials: ResMut<Assets<ColorMaterial>>
+) {
+    // placed clear of the enemy formation, which would otherwise fall in
+    let planet = Vec2::new(700., 400.);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(Circle::new(80.))),
+            material: materials.add(Color::rgb(0.35, 0.45, 0.8)),
+            ..default()
+        },
+        Position { current: planet, previous: planet },
+        Velocity(Vec2::ZERO),
+        ForceField { radius: 300., strength: 600., falloff: Falloff::InverseSquare(80.) },
+        Collider::Circle(Circle::new(80.)),
+        CollisionLayers::new(Layers::HAZARD, Layers::PLAYER | Layers::PLAYER_PROJECTILE),
+        RigidBody { restitution: 0.3 },
+        Mass::new(f32::INFINITY),
+    ));
+
+    let black_hole = Vec2::new(-700., 600.);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(Circle::new(12.))),
+            material: materials.add(Color::rgb(0.25, 0.05, 0.3)),
+            ..default()
+        },
+        Position { current: black_hole, previous: black_hole },
+        ForceField { radius: 300., strength: 20000., falloff: Falloff::InverseSquare(12.) },
+    ));
+
+    let repulsor = Vec2::new(0., -400.);
+    commands.spawn((
+        MaterialMesh2dBundle {
+            mesh: Mesh2dHandle(meshes.add(Circle::new(150.))),
+            material: materials.add(Color::rgba(1., 0.5, 0.2, 0.08)),
+            ..default()
+        },
+        Position { current: repulsor, previous: repulsor },
+        ForceField { radius: 150., strength: -1500., falloff: Falloff::Linear },
+    ));
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nothing_happens_outside_the_radius() {
+        let field = ForceField { radius: 100., strength: 50., falloff: Falloff::InverseSquare(10.) };
+        assert_eq!(field.acceleration(Vec2::ZERO, Vec2::new(0., 101.)), Vec2::ZERO);
+        assert!(field.acceleration(Vec2::ZERO, Vec2::new(0., 100.)).abs_diff_eq(Vec2::new(0., -0.5), 1e-4));
+    }
+
+    #[test]
+    fn gravity_falls_off_with_the_square_of_distance() {
+        let planet = ForceField { radius: 1000., strength: 400., falloff: Falloff::InverseSquare(50.) };
+        assert!(planet.acceleration(Vec2::ZERO, Vec2::new(100., 0.)).abs_diff_eq(Vec2::new(-100., 0.), 1e-3));
+        // capped at surface strength, and no direction at the very centre
+        assert!(planet.acceleration(Vec2::ZERO, Vec2::new(10., 0.)).abs_diff_eq(Vec2::new(-400., 0.), 1e-3));
+        assert_eq!(planet.acceleration(Vec2::ZERO, Vec2::ZERO), Vec2::ZERO);
+    }
+
+    #[test]
+    fn repulsor_pushes_away_and_fades_to_the_edge() {
+        let repulsor = ForceField { radius: 100., strength: -200., falloff: Falloff::Linear };
+        assert!(repulsor.acceleration(Vec2::ZERO, Vec2::new(0., 25.)).abs_diff_eq(Vec2::new(0., 150.), 1e-3));
+        assert!(repulsor.acceleration(Vec2::ZERO, Vec2::new(0., 100.)).abs_diff_eq(Vec2::ZERO, 1e-3));
+    }
+}
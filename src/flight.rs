@@ -0,0 +1,178 @@
+use std::fs;
+use bevy::prelude::*;
+use serde::Deserialize;
+
+/// How a ship turns its pilot's input into acceleration.
+///
+/// Thrust is split along the ship's own axes: forward (the way its nose points) and strafe
+/// (sideways). With flight assist on, the model brakes along every axis the pilot isn't
+/// thrusting on and holds the ship under its speed cap, like arcade handling. With it off,
+/// the ship only ever accelerates where it is pushed and drifts otherwise.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct FlightModel {
+    pub forward_thrust: f32,
+    pub reverse_thrust: f32,
+    pub strafe_thrust: f32,
+    pub speed_cap: SpeedCap,
+    pub braking: Braking,
+    pub assist: bool,
+}
+
+impl Default for FlightModel {
+    fn default() -> Self {
+        FlightModel {
+            forward_thrust: 2500.,
+            reverse_thrust: 2500.,
+            strafe_thrust: 2500.,
+            speed_cap: SpeedCap::Vector(400.),
+            braking: Braking { deceleration: 200., drag: 0. },
+            assist: true,
+        }
+    }
+}
+
+/// Top speed under flight assist.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum SpeedCap {
+    /// Caps forward and strafe speed separately, so flying diagonally is faster.
+    PerAxis(f32),
+    /// Caps the speed in any direction.
+    Vector(f32),
+}
+
+/// How flight assist slows an axis the pilot lets go of: a steady deceleration, plus optional drag
+/// that grows with speed so fast ships shed speed quickly and then coast to a gentle stop.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub struct Braking {
+    pub deceleration: f32,
+    /// Extra deceleration per unit of speed.
+    pub drag: f32,
+}
+
+impl Braking {
+    /// Speed along one axis after braking for `dt`. Never overshoots past a standstill.
+    fn slow(&self, speed: f32, dt: f32) -> f32 {
+        let loss = (self.deceleration + self.drag * speed.abs()) * dt;
+        speed.signum() * (speed.abs() - loss).max(0.)
+    }
+}
+
+impl FlightModel {
+    /// Reads a flight model from a RON file. Settings left out keep their default.
+    pub fn load(path: &str) -> Self {
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("could not read {path} ({err}), using the default flight model");
+                return FlightModel::default();
+            }
+        };
+        ron::from_str(&contents).unwrap_or_else(|err| {
+            warn!("could not parse {path} ({err}), using the default flight model");
+            FlightModel::default()
+        })
+    }
+
+    /// Acceleration over the next tick of length `dt`. `movement` is the pilot's input in world
+    /// space, `facing` the unit vector the ship's nose points along.
+    pub fn acceleration(&self, velocity: Vec2, movement: Vec2, facing: Vec2, dt: f32) -> Vec2 {
+        let strafe = Vec2::new(facing.y, -facing.x);
+        let forward_input = movement.dot(facing);
+        let strafe_input = movement.dot(strafe);
+        let forward_thrust = if forward_input >= 0. { self.forward_thrust } else { self.reverse_thrust };
+        let thrust = facing * forward_input * forward_thrust + strafe * strafe_input * self.strafe_thrust;
+        if !self.assist || dt <= 0. {
+            return thrust;
+        }
+
+        let mut forward_speed = (velocity + thrust * dt).dot(facing);
+        let mut strafe_speed = (velocity + thrust * dt).dot(strafe);
+        if forward_input == 0. {
+            forward_speed = self.braking.slow(forward_speed, dt);
+        }
+        if strafe_input == 0. {
+            strafe_speed = self.braking.slow(strafe_speed, dt);
+        }
+        let target = match self.speed_cap {
+            SpeedCap::PerAxis(max) => facing * forward_speed.clamp(-max, max) + strafe * strafe_speed.clamp(-max, max),
+            SpeedCap::Vector(max) => (facing * forward_speed + strafe * strafe_speed).clamp_length_max(max),
+        };
+        (target - velocity) / dt
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DT: f32 = 1. / 64.;
+
+    #[test]
+    fn shipped_flight_model_parses() {
+        let shipped = fs::read_to_string("assets/flight.ron").unwrap();
+        assert!(ron::from_str::<FlightModel>(&shipped).is_ok());
+    }
+
+    /// Velocity after holding `movement` for `seconds`, starting from `velocity`.
+    fn fly(model: &FlightModel, mut velocity: Vec2, movement: Vec2, seconds: f32) -> Vec2 {
+        for _ in 0..(seconds / DT) as u32 {
+            velocity += model.acceleration(velocity, movement, Vec2::Y, DT) * DT;
+        }
+        velocity
+    }
+
+    #[test]
+    fn vector_cap_holds_diagonals_to_top_speed() {
+        let model = FlightModel { speed_cap: SpeedCap::Vector(400.), ..default() };
+        let velocity = fly(&model, Vec2::ZERO, Vec2::ONE.normalize(), 2.);
+        assert!((velocity.length() - 400.).abs() < 1e-2, "{velocity}");
+    }
+
+    #[test]
+    fn per_axis_cap_lets_diagonals_go_faster() {
+        let model = FlightModel { speed_cap: SpeedCap::PerAxis(400.), ..default() };
+        let velocity = fly(&model, Vec2::ZERO, Vec2::ONE, 2.);
+        assert!(velocity.abs_diff_eq(Vec2::splat(400.), 1e-2), "{velocity}");
+    }
+
+    #[test]
+    fn assist_brakes_only_the_axis_let_go_of() {
+        let model = FlightModel::default();
+        // still pushing right while no longer holding up
+        let velocity = fly(&model, Vec2::new(200., 200.), Vec2::X, 0.25);
+        assert!(velocity.y < 200. && velocity.y > 0., "{velocity}");
+        assert!(velocity.x > 200., "{velocity}");
+    }
+
+    #[test]
+    fn braking_curve_slows_fast_ships_harder_and_stops_dead() {
+        let braking = Braking { deceleration: 100., drag: 1. };
+        let fast_loss = 400. - braking.slow(400., DT);
+        let slow_loss = 50. - braking.slow(50., DT);
+        assert!(fast_loss > slow_loss);
+        assert_eq!(braking.slow(1., DT), 0.);
+        assert_eq!(braking.slow(-1., DT), 0.);
+
+        let model = FlightModel { braking, ..default() };
+        assert_eq!(fly(&model, Vec2::new(-300., 300.), Vec2::ZERO, 3.), Vec2::ZERO);
+    }
+
+    #[test]
+    fn strafe_and_reverse_thrust_are_separate() {
+        let model = FlightModel { forward_thrust: 1000., reverse_thrust: 250., strafe_thrust: 500., assist: false, ..default() };
+        assert_eq!(model.acceleration(Vec2::ZERO, Vec2::Y, Vec2::Y, DT), Vec2::new(0., 1000.));
+        assert_eq!(model.acceleration(Vec2::ZERO, -Vec2::Y, Vec2::Y, DT), Vec2::new(0., -250.));
+        assert_eq!(model.acceleration(Vec2::ZERO, Vec2::X, Vec2::Y, DT), Vec2::new(500., 0.));
+        // turned to face right, pushing right is forward thrust
+        assert!(model.acceleration(Vec2::ZERO, Vec2::X, Vec2::X, DT).abs_diff_eq(Vec2::new(1000., 0.), 1e-3));
+    }
+
+    #[test]
+    fn without_assist_the_ship_drifts() {
+        let model = FlightModel { assist: false, ..default() };
+        assert_eq!(fly(&model, Vec2::new(300., -50.), Vec2::ZERO, 1.), Vec2::new(300., -50.));
+        // no speed cap either
+        assert!(fly(&model, Vec2::ZERO, Vec2::Y, 1.).y > 2000.);
+    }
+}
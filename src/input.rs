@@ -1,44 +1,164 @@
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::hash::Hash;
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
+use bevy::window::PrimaryWindow;
+use serde::{Deserialize, Serialize};
 
-pub struct InputPlugin;
+pub const MAX_PLAYERS: usize = 4;
+
+/// Control scheme of the first player; the others play [`ControlScheme::Classic`],
+/// since there is only one mouse.
+#[derive(Default)]
+pub struct InputPlugin {
+    pub scheme: ControlScheme,
+}
 
 impl Plugin for InputPlugin {
     fn build(&self, app: &mut App) {
+        let scheme = self.scheme;
         app
-            .add_systems(Startup, setup)
-            .add_systems(Update, handle_input);
+            .add_systems(PreUpdate, attach_player_input(scheme))
+            .add_systems(Update, handle_input.in_set(InputSet))
+            .add_systems(FixedPreUpdate, latch_input.in_set(InputSet));
     }
 }
 
 
-fn setup(mut commands: Commands) {
-    commands.spawn(InputState {
-        up: false,
-        down: false,
-        left: false,
-        right: false,
-        idle: true,
-        shooting: false,
-        boost: false
-    });
+fn attach_player_input(
+    scheme: ControlScheme
+) -> impl FnMut(Commands, Query<(Entity, &PlayerSlot), Without<InputState>>, Query<&PlayerSlot>) {
+    move |mut commands, q_players, q_slots| {
+        let alone = !q_slots.iter().any(|slot| slot.0 == 1);
+        for (entity, slot) in q_players.iter() {
+            let mut bindings = InputBindings::load(&slot.bindings_path(), *slot);
+            if slot.0 == 0 && alone {
+                // nobody needs the second player's keys, so arrow key players can use them
+                bindings.add_keys_of(&InputBindings::load(&PlayerSlot(1).bindings_path(), PlayerSlot(1)));
+            }
+            commands.entity(entity).insert((
+                InputState::default(),
+                ActionBuffer::default(),
+                bindings,
+                if slot.0 == 0 { scheme } else { ControlScheme::Classic }
+            ));
+        }
+    }
 }
 
+/// Samples devices every frame, collecting edges into the [`ActionBuffer`] until the next fixed tick.
 fn handle_input(
-    key: Res<ButtonInput<KeyCode>>,
-    mut input_state: Query<&mut InputState>
+    devices: InputDevices,
+    gamepads: Res<Gamepads>,
+    gamepad_axes: Res<Axis<GamepadAxis>>,
+    q_window: Query<&Window, With<PrimaryWindow>>,
+    q_camera: Query<(&Transform, &OrthographicProjection), With<bevy::prelude::Camera>>,
+    mut input: Query<(&mut ActionBuffer, &InputBindings, &ControlScheme, &PlayerSlot)>
+) {
+    // players pick up gamepads in the order they were connected
+    let mut gamepads: Vec<Gamepad> = gamepads.iter().collect();
+    gamepads.sort_by_key(|gamepad| gamepad.id);
+
+    for (mut buffer, bindings, scheme, slot) in input.iter_mut() {
+        let gamepad = gamepads.get(slot.0).copied();
+        sample_devices(&mut buffer, bindings, &devices, gamepad, &gamepad_axes);
+        buffer.aim = match scheme {
+            ControlScheme::Classic => None,
+            ControlScheme::TwinStick => cursor_world_position(&q_window, &q_camera).or(buffer.aim),
+        };
+    }
+}
+
+fn sample_devices(
+    buffer: &mut ActionBuffer,
+    bindings: &InputBindings,
+    devices: &InputDevices,
+    gamepad: Option<Gamepad>,
+    gamepad_axes: &Axis<GamepadAxis>
 ) {
-    let mut input_state = input_state.single_mut();
-    input_state.up = key.pressed(KeyCode::KeyW);
-    input_state.down = key.pressed(KeyCode::KeyS);
-    input_state.left = key.pressed(KeyCode::KeyA);
-    input_state.right = key.pressed(KeyCode::KeyD);
-    input_state.idle = !key.pressed(KeyCode::KeyW) && !key.pressed(KeyCode::KeyS) && !key.pressed(KeyCode::KeyA) && !key.pressed(KeyCode::KeyD);
-    input_state.shooting = key.pressed(KeyCode::Space);
-    input_state.boost = key.pressed(KeyCode::KeyW) && key.pressed(KeyCode::ShiftLeft);
+    for action in InputAction::ALL {
+        let held = bindings.pressed(action, devices, gamepad);
+        let was_held = buffer.held.contains(&action);
+        // a tap within one frame is never seen held, only by its edges. Other edges come from
+        // the action as a whole, so a second binding going down or up while one is held is no edge.
+        let tapped = !held && !was_held
+            && bindings.just_pressed(action, devices, gamepad)
+            && bindings.just_released(action, devices, gamepad);
+        if (held && !was_held) || tapped {
+            buffer.pressed.insert(action);
+        }
+        if (!held && was_held) || tapped {
+            buffer.released.insert(action);
+        }
+        if held {
+            buffer.held.insert(action);
+        } else {
+            buffer.held.remove(&action);
+        }
+    }
+
+    buffer.stick = match gamepad {
+        Some(gamepad) => Vec2::new(
+            gamepad_axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.),
+            gamepad_axes.get(GamepadAxis::new(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.)
+        ),
+        None => Vec2::ZERO,
+    };
+}
+
+/// Maps the cursor through the camera's orthographic projection into world space.
+fn cursor_world_position(
+    q_window: &Query<&Window, With<PrimaryWindow>>,
+    q_camera: &Query<(&Transform, &OrthographicProjection), With<bevy::prelude::Camera>>
+) -> Option<Vec2> {
+    let window = q_window.get_single().ok()?;
+    let cursor = window.cursor_position()?;
+    let (camera_transform, projection) = q_camera.get_single().ok()?;
+    // window coordinates grow downwards, world coordinates upwards
+    let fraction = Vec2::new(cursor.x / window.width(), 1. - cursor.y / window.height());
+    Some(camera_transform.translation.truncate() + projection.area.min + fraction * projection.area.size())
 }
 
-#[derive(Component)]
+/// Turns everything buffered since the previous tick into the `InputState` the fixed systems read.
+fn latch_input(mut input: Query<(&mut InputState, &mut ActionBuffer)>) {
+    for (mut input_state, mut buffer) in input.iter_mut() {
+        latch(&mut input_state, &mut buffer);
+    }
+}
+
+fn latch(input_state: &mut InputState, buffer: &mut ActionBuffer) {
+    for action in InputAction::ALL {
+        let was_pressed = input_state.action(action).pressed;
+        let pressed_since = buffer.pressed.contains(&action);
+        let pressed = pressed_since || buffer.held.contains(&action);
+        input_state.actions.insert(action, ActionButton {
+            pressed,
+            just_pressed: pressed_since,
+            // a fresh press while the previous tick still saw it down implies a release in between
+            just_released: was_pressed && (!pressed || pressed_since || buffer.released.contains(&action)),
+        });
+    }
+    buffer.pressed.clear();
+    buffer.released.clear();
+
+    let pressed = |action| input_state.action(action).pressed;
+    let digital_axis = |positive, negative| (pressed(positive) as i8 - pressed(negative) as i8) as f32;
+    let movement = Vec2::new(
+        digital_axis(InputAction::Right, InputAction::Left),
+        digital_axis(InputAction::Up, InputAction::Down)
+    ) + buffer.stick;
+    input_state.set_movement(movement);
+    input_state.shooting = input_state.action(InputAction::Shoot).pressed;
+    input_state.boost = input_state.up && input_state.action(InputAction::Boost).pressed;
+    input_state.aim = buffer.aim;
+}
+
+#[derive(Component, Serialize, Deserialize, Clone, Debug)]
+#[serde(default)]
 pub struct InputState {
+    /// Requested thrust per axis in `-1..=1`. Keys give full deflection, sticks anything in between.
+    pub movement: Vec2,
     pub up: bool,
     pub down: bool,
     pub left: bool,
@@ -46,8 +166,517 @@ pub struct InputState {
     pub idle: bool,
     pub shooting: bool,
     pub boost: bool,
+    /// World-space point to shoot at; only set in [`ControlScheme::TwinStick`].
+    pub aim: Option<Vec2>,
+    pub actions: HashMap<InputAction, ActionButton>,
+}
+
+impl InputState {
+    pub fn action(&self, action: InputAction) -> ActionButton {
+        self.actions.get(&action).copied().unwrap_or_default()
+    }
+
+    /// Sets the movement vector, clamped per axis, along with the directional flags derived from it.
+    pub fn set_movement(&mut self, movement: Vec2) {
+        self.movement = movement.clamp(Vec2::NEG_ONE, Vec2::ONE);
+        self.up = self.movement.y > 0.;
+        self.down = self.movement.y < 0.;
+        self.left = self.movement.x < 0.;
+        self.right = self.movement.x > 0.;
+        self.idle = !self.up && !self.down && !self.left && !self.right;
+    }
+}
+
+impl Default for InputState {
+    fn default() -> Self {
+        InputState {
+            movement: Vec2::ZERO,
+            up: false,
+            down: false,
+            left: false,
+            right: false,
+            idle: true,
+            shooting: false,
+            boost: false,
+            aim: None,
+            actions: HashMap::new()
+        }
+    }
+}
+
+/// State of one action for the current fixed tick.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct ActionButton {
+    /// Held now, or tapped at any point since the previous tick.
+    pub pressed: bool,
+    pub just_pressed: bool,
+    pub just_released: bool,
+}
+
+/// Device state gathered between fixed ticks, so taps shorter than a tick are not lost.
+#[derive(Component, Default)]
+pub struct ActionBuffer {
+    held: HashSet<InputAction>,
+    pressed: HashSet<InputAction>,
+    released: HashSet<InputAction>,
+    stick: Vec2,
+    aim: Option<Vec2>,
 }
 
+impl ActionBuffer {
+    /// Moves the buffered aim point along with the world when the floating origin moves.
+    pub fn shift_aim(&mut self, shift: Vec2) {
+        if let Some(aim) = &mut self.aim {
+            *aim -= shift;
+        }
+    }
+}
+
+/// How the player steers and aims.
+#[derive(Component, Default, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ControlScheme {
+    /// Move and fire straight ahead.
+    #[default]
+    Classic,
+    /// Move with the movement bindings, fire towards the mouse cursor.
+    TwinStick,
+}
+
+impl ControlScheme {
+    /// Picks [`ControlScheme::TwinStick`] when `--twin-stick` is on the command line.
+    pub fn from_args() -> Self {
+        if std::env::args().any(|arg| arg == "--twin-stick") {
+            ControlScheme::TwinStick
+        } else {
+            ControlScheme::Classic
+        }
+    }
+}
+
+#[derive(SystemParam)]
+pub struct InputDevices<'w> {
+    keys: Res<'w, ButtonInput<KeyCode>>,
+    mouse_buttons: Res<'w, ButtonInput<MouseButton>>,
+    gamepad_buttons: Res<'w, ButtonInput<GamepadButton>>,
+}
+
+/// Which local player an entity belongs to, counting from 0. Entities with a slot get
+/// that player's input components attached.
+#[derive(Component, Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct PlayerSlot(pub usize);
+
+impl PlayerSlot {
+    /// Reads `--players <n>` from the command line, clamped to `1..=MAX_PLAYERS`.
+    pub fn count_from_args() -> usize {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            if arg == "--players" {
+                return args.next()
+                    .and_then(|count| count.parse().ok())
+                    .map_or(1, |count: usize| count.clamp(1, MAX_PLAYERS));
+            }
+        }
+        1
+    }
 
+    /// `assets/controls.ron` for the first player, `assets/controls_p<n>.ron` for the others.
+    pub fn bindings_path(self) -> String {
+        match self.0 {
+            0 => "assets/controls.ron".to_string(),
+            slot => format!("assets/controls_p{}.ron", slot + 1),
+        }
+    }
+}
 
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct InputSet;
+
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum InputAction {
+    Up,
+    Down,
+    Left,
+    Right,
+    Shoot,
+    Boost,
+    /// Switches the ship's flight assist on or off.
+    FlightAssist,
+    /// Cycles through the ship's weapons.
+    SwitchWeapon,
+}
+
+impl InputAction {
+    pub const ALL: [InputAction; 8] = [
+        InputAction::Up,
+        InputAction::Down,
+        InputAction::Left,
+        InputAction::Right,
+        InputAction::Shoot,
+        InputAction::Boost,
+        InputAction::FlightAssist,
+        InputAction::SwitchWeapon,
+    ];
+
+    /// The first player gets the left side of the keyboard and the mouse, the second the arrow
+    /// keys and the right side. Every player can use their own gamepad.
+    fn default_bindings(self, slot: PlayerSlot) -> Vec<Binding> {
+        let keyboard = match (slot.0, self) {
+            (0, InputAction::Up) => vec![Binding::Key(KeyCode::KeyW)],
+            (0, InputAction::Down) => vec![Binding::Key(KeyCode::KeyS)],
+            (0, InputAction::Left) => vec![Binding::Key(KeyCode::KeyA)],
+            (0, InputAction::Right) => vec![Binding::Key(KeyCode::KeyD)],
+            (0, InputAction::Shoot) => vec![Binding::Key(KeyCode::Space), Binding::Mouse(MouseButton::Left)],
+            (0, InputAction::Boost) => vec![Binding::Key(KeyCode::ShiftLeft)],
+            (0, InputAction::FlightAssist) => vec![Binding::Key(KeyCode::KeyF)],
+            (0, InputAction::SwitchWeapon) => vec![Binding::Key(KeyCode::KeyQ), Binding::Mouse(MouseButton::Right)],
+            (1, InputAction::Up) => vec![Binding::Key(KeyCode::ArrowUp)],
+            (1, InputAction::Down) => vec![Binding::Key(KeyCode::ArrowDown)],
+            (1, InputAction::Left) => vec![Binding::Key(KeyCode::ArrowLeft)],
+            (1, InputAction::Right) => vec![Binding::Key(KeyCode::ArrowRight)],
+            (1, InputAction::Shoot) => vec![Binding::Key(KeyCode::ControlRight)],
+            (1, InputAction::Boost) => vec![Binding::Key(KeyCode::ShiftRight)],
+            (1, InputAction::FlightAssist) => vec![Binding::Key(KeyCode::End)],
+            (1, InputAction::SwitchWeapon) => vec![Binding::Key(KeyCode::PageDown)],
+            _ => vec![],
+        };
+        let gamepad = match self {
+            InputAction::Up => vec![Binding::Gamepad(GamepadButtonType::DPadUp)],
+            InputAction::Down => vec![Binding::Gamepad(GamepadButtonType::DPadDown)],
+            InputAction::Left => vec![Binding::Gamepad(GamepadButtonType::DPadLeft)],
+            InputAction::Right => vec![Binding::Gamepad(GamepadButtonType::DPadRight)],
+            InputAction::Shoot => vec![Binding::Gamepad(GamepadButtonType::RightTrigger2), Binding::Gamepad(GamepadButtonType::South)],
+            InputAction::Boost => vec![Binding::Gamepad(GamepadButtonType::LeftTrigger2)],
+            InputAction::FlightAssist => vec![Binding::Gamepad(GamepadButtonType::North)],
+            InputAction::SwitchWeapon => vec![Binding::Gamepad(GamepadButtonType::West)],
+        };
+        [keyboard, gamepad].concat()
+    }
+}
+
+/// A single physical input that can trigger an action.
+#[derive(Serialize, Deserialize, Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Binding {
+    Key(KeyCode),
+    Mouse(MouseButton),
+    /// Analog triggers count as pressed past the gamepad's press threshold.
+    Gamepad(GamepadButtonType),
+}
+
+/// A player's binding profile: inputs bound to each action. Any of an action's bindings triggers it.
+#[derive(Component, Serialize, Deserialize, Debug, Clone)]
+#[serde(transparent)]
+pub struct InputBindings(pub HashMap<InputAction, Vec<Binding>>);
+
+impl InputBindings {
+    pub fn default_for(slot: PlayerSlot) -> Self {
+        InputBindings(InputAction::ALL.iter().map(|action| (*action, action.default_bindings(slot))).collect())
+    }
+
+    /// Reads bindings from a RON file, falling back to the slot's defaults when the file
+    /// is missing or malformed. Actions left out of the file keep their default bindings.
+    pub fn load(path: &str, slot: PlayerSlot) -> Self {
+        let mut bindings = Self::default_for(slot);
+        let contents = match fs::read_to_string(path) {
+            Ok(contents) => contents,
+            Err(err) => {
+                warn!("could not read {path} ({err}), using default controls");
+                return bindings;
+            }
+        };
+        match ron::from_str::<InputBindings>(&contents) {
+            Ok(loaded) => bindings.0.extend(loaded.0),
+            Err(err) => warn!("could not parse {path} ({err}), using default controls"),
+        }
+        bindings
+    }
+
+    /// Adds the keyboard bindings of `other` to these, action by action.
+    pub fn add_keys_of(&mut self, other: &InputBindings) {
+        for (action, other_bindings) in &other.0 {
+            let bindings = self.0.entry(*action).or_default();
+            for binding in other_bindings {
+                if matches!(binding, Binding::Key(_)) && !bindings.contains(binding) {
+                    bindings.push(*binding);
+                }
+            }
+        }
+    }
+
+    pub fn pressed(&self, action: InputAction, devices: &InputDevices, gamepad: Option<Gamepad>) -> bool {
+        self.any(action, devices, gamepad, Edge::Held)
+    }
 
+    /// Whether any binding went down this frame, even if it also came back up.
+    pub fn just_pressed(&self, action: InputAction, devices: &InputDevices, gamepad: Option<Gamepad>) -> bool {
+        self.any(action, devices, gamepad, Edge::Pressed)
+    }
+
+    /// Whether any binding came up this frame, even if it also went down.
+    pub fn just_released(&self, action: InputAction, devices: &InputDevices, gamepad: Option<Gamepad>) -> bool {
+        self.any(action, devices, gamepad, Edge::Released)
+    }
+
+    fn any(&self, action: InputAction, devices: &InputDevices, gamepad: Option<Gamepad>, edge: Edge) -> bool {
+        self.0.get(&action).is_some_and(|bindings| bindings.iter().any(|binding| match binding {
+            Binding::Key(key) => edge.of(&devices.keys, *key),
+            Binding::Mouse(button) => edge.of(&devices.mouse_buttons, *button),
+            Binding::Gamepad(button) => gamepad.is_some_and(|gamepad| edge.of(&devices.gamepad_buttons, GamepadButton::new(gamepad, *button))),
+        }))
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Edge {
+    Held,
+    Pressed,
+    Released,
+}
+
+impl Edge {
+    fn of<T: Copy + Eq + Hash + Send + Sync + 'static>(self, input: &ButtonInput<T>, button: T) -> bool {
+        match self {
+            Edge::Held => input.pressed(button),
+            Edge::Pressed => input.just_pressed(button),
+            Edge::Released => input.just_released(button),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::input::gamepad::{GamepadConnection, GamepadConnectionEvent, GamepadEvent, GamepadInfo};
+    use bevy::input::gamepad::{GamepadAxisChangedEvent, GamepadButtonChangedEvent};
+    use bevy::input::keyboard::{Key, KeyboardInput};
+    use bevy::input::ButtonState;
+    use bevy::time::TimeUpdateStrategy;
+    use super::*;
+
+    fn app_with_gamepad() -> (App, Gamepad) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, bevy::input::InputPlugin, InputPlugin::default()));
+        app.world.spawn(PlayerSlot(0));
+        let gamepad = connect_gamepad(&mut app, 0);
+        (app, gamepad)
+    }
+
+    fn connect_gamepad(app: &mut App, id: usize) -> Gamepad {
+        let gamepad = Gamepad::new(id);
+        app.world.send_event(GamepadEvent::Connection(GamepadConnectionEvent::new(
+            gamepad,
+            GamepadConnection::Connected(GamepadInfo { name: "synthetic".into() })
+        )));
+        frame(app);
+        gamepad
+    }
+
+    /// Runs one frame without a fixed tick.
+    fn frame(app: &mut App) {
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+    }
+
+    /// Runs one frame containing exactly one fixed tick.
+    fn tick(app: &mut App) {
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.update();
+    }
+
+    fn input_state(app: &mut App) -> &InputState {
+        player_input_state(app, PlayerSlot(0))
+    }
+
+    fn player_input_state(app: &mut App, slot: PlayerSlot) -> &InputState {
+        app.world.query::<(&InputState, &PlayerSlot)>()
+            .iter(&app.world)
+            .find_map(|(input_state, player)| (*player == slot).then_some(input_state))
+            .unwrap()
+    }
+
+    fn keys(app: &mut App) -> Mut<'_, ButtonInput<KeyCode>> {
+        app.world.resource_mut::<ButtonInput<KeyCode>>()
+    }
+
+    #[test]
+    fn shipped_bindings_parse() {
+        for slot in [PlayerSlot(0), PlayerSlot(1)] {
+            let contents = fs::read_to_string(slot.bindings_path()).unwrap();
+            assert!(ron::from_str::<InputBindings>(&contents).is_ok());
+        }
+    }
+
+    #[test]
+    fn single_player_also_gets_the_second_players_keys() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, bevy::input::InputPlugin, InputPlugin::default()));
+        app.world.spawn(PlayerSlot(0));
+        keys(&mut app).press(KeyCode::ArrowUp);
+        frame(&mut app);
+        tick(&mut app);
+        assert_eq!(input_state(&mut app).movement, Vec2::Y);
+
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, bevy::input::InputPlugin, InputPlugin::default()));
+        app.world.spawn(PlayerSlot(0));
+        app.world.spawn(PlayerSlot(1));
+        keys(&mut app).press(KeyCode::ArrowUp);
+        frame(&mut app);
+        tick(&mut app);
+        assert_eq!(input_state(&mut app).movement, Vec2::ZERO);
+        assert_eq!(player_input_state(&mut app, PlayerSlot(1)).movement, Vec2::Y);
+    }
+
+    #[test]
+    fn players_read_their_own_gamepad_and_keys() {
+        let (mut app, _) = app_with_gamepad();
+        app.world.spawn(PlayerSlot(1));
+        let second_gamepad = connect_gamepad(&mut app, 1);
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(second_gamepad, GamepadAxisType::LeftStickX, 1.)));
+        keys(&mut app).press(KeyCode::Space);
+        frame(&mut app);
+        tick(&mut app);
+
+        let first = input_state(&mut app);
+        assert_eq!(first.movement, Vec2::ZERO);
+        assert!(first.shooting);
+        let second = player_input_state(&mut app, PlayerSlot(1));
+        assert_eq!(second.movement, Vec2::X);
+        assert!(!second.shooting);
+    }
+
+    #[test]
+    fn stick_deflection_gives_analog_movement() {
+        let (mut app, gamepad) = app_with_gamepad();
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickX, -0.5)));
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickY, 0.25)));
+        frame(&mut app);
+        tick(&mut app);
+
+        let input_state = input_state(&mut app);
+        assert_eq!(input_state.movement, Vec2::new(-0.5, 0.25));
+        assert!(input_state.left && input_state.up && !input_state.idle);
+    }
+
+    #[test]
+    fn trigger_buttons_set_flags() {
+        let (mut app, gamepad) = app_with_gamepad();
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickY, 1.)));
+        app.world.send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::RightTrigger2, 1.)));
+        app.world.send_event(GamepadEvent::Button(GamepadButtonChangedEvent::new(gamepad, GamepadButtonType::LeftTrigger2, 1.)));
+        frame(&mut app);
+        tick(&mut app);
+
+        let input_state = input_state(&mut app);
+        assert!(input_state.shooting);
+        assert!(input_state.boost);
+    }
+
+    #[test]
+    fn released_stick_is_idle() {
+        let (mut app, gamepad) = app_with_gamepad();
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickX, 1.)));
+        frame(&mut app);
+        tick(&mut app);
+        app.world.send_event(GamepadEvent::Axis(GamepadAxisChangedEvent::new(gamepad, GamepadAxisType::LeftStickX, 0.)));
+        frame(&mut app);
+        tick(&mut app);
+
+        let input_state = input_state(&mut app);
+        assert_eq!(input_state.movement, Vec2::ZERO);
+        assert!(input_state.idle);
+    }
+
+    #[test]
+    fn tap_within_one_frame_is_not_lost() {
+        let (mut app, _) = app_with_gamepad();
+        for state in [ButtonState::Pressed, ButtonState::Released] {
+            app.world.send_event(KeyboardInput {
+                key_code: KeyCode::Space,
+                logical_key: Key::Space,
+                state,
+                window: Entity::PLACEHOLDER,
+            });
+        }
+        frame(&mut app);
+        assert!(!keys(&mut app).pressed(KeyCode::Space));
+
+        tick(&mut app);
+        let shoot = input_state(&mut app).action(InputAction::Shoot);
+        assert_eq!(shoot, ActionButton { pressed: true, just_pressed: true, just_released: false });
+        tick(&mut app);
+        let shoot = input_state(&mut app).action(InputAction::Shoot);
+        assert_eq!(shoot, ActionButton { pressed: false, just_pressed: false, just_released: true });
+    }
+
+    #[test]
+    fn second_binding_of_a_held_action_makes_no_edges() {
+        let (mut app, _) = app_with_gamepad();
+        fn shoot_after(app: &mut App, change: impl FnOnce(&mut App)) -> ActionButton {
+            change(app);
+            frame(app);
+            tick(app);
+            input_state(app).action(InputAction::Shoot)
+        }
+        // sent as events, so the key shows as just pressed and just released like a real one
+        let space = |state| move |app: &mut App| {
+            app.world.send_event(KeyboardInput { key_code: KeyCode::Space, logical_key: Key::Space, state, window: Entity::PLACEHOLDER });
+        };
+        let held = ActionButton { pressed: true, just_pressed: false, just_released: false };
+
+        let shoot = shoot_after(&mut app, |app| app.world.resource_mut::<ButtonInput<MouseButton>>().press(MouseButton::Left));
+        assert_eq!(shoot, ActionButton { pressed: true, just_pressed: true, just_released: false });
+        assert_eq!(shoot_after(&mut app, space(ButtonState::Pressed)), held);
+        assert_eq!(shoot_after(&mut app, space(ButtonState::Released)), held);
+        let shoot = shoot_after(&mut app, |app| app.world.resource_mut::<ButtonInput<MouseButton>>().release(MouseButton::Left));
+        assert_eq!(shoot, ActionButton { pressed: false, just_pressed: false, just_released: true });
+    }
+
+    #[test]
+    fn tap_between_ticks_is_not_lost() {
+        let (mut app, _) = app_with_gamepad();
+        keys(&mut app).press(KeyCode::Space);
+        frame(&mut app);
+        keys(&mut app).release(KeyCode::Space);
+        frame(&mut app);
+
+        tick(&mut app);
+        let shoot = input_state(&mut app).action(InputAction::Shoot);
+        assert_eq!(shoot, ActionButton { pressed: true, just_pressed: true, just_released: false });
+        assert!(input_state(&mut app).shooting);
+
+        tick(&mut app);
+        let shoot = input_state(&mut app).action(InputAction::Shoot);
+        assert_eq!(shoot, ActionButton { pressed: false, just_pressed: false, just_released: true });
+    }
+
+    #[test]
+    fn held_action_is_just_pressed_once() {
+        let (mut app, _) = app_with_gamepad();
+        keys(&mut app).press(KeyCode::ShiftLeft);
+        frame(&mut app);
+
+        tick(&mut app);
+        assert!(input_state(&mut app).action(InputAction::Boost).just_pressed);
+        tick(&mut app);
+        let boost = input_state(&mut app).action(InputAction::Boost);
+        assert_eq!(boost, ActionButton { pressed: true, just_pressed: false, just_released: false });
+    }
+
+    #[test]
+    fn repress_within_a_tick_reports_both_edges() {
+        let (mut app, _) = app_with_gamepad();
+        keys(&mut app).press(KeyCode::KeyW);
+        frame(&mut app);
+        tick(&mut app);
+
+        keys(&mut app).release(KeyCode::KeyW);
+        frame(&mut app);
+        keys(&mut app).press(KeyCode::KeyW);
+        frame(&mut app);
+        tick(&mut app);
+
+        let up = input_state(&mut app).action(InputAction::Up);
+        assert_eq!(up, ActionButton { pressed: true, just_pressed: true, just_released: true });
+    }
+}
@@ -1,21 +1,41 @@
+#![allow(clippy::type_complexity, clippy::too_many_arguments)]
+
 mod camera;
 mod spaceship;
 mod input;
 mod bullet;
+mod beam;
+mod broadphase;
+mod collider;
 mod stars;
 mod physics;
 mod enemy;
+mod field;
+mod missile;
+mod flight;
 mod resource_manager;
 mod explosion;
+mod origin;
+mod replay;
+mod snapshot;
+mod controller;
+mod weapon;
 
-use crate::input::InputPlugin;
+use crate::input::{ControlScheme, InputPlugin, PlayerSlot};
 use bevy::prelude::*;
+use crate::beam::BeamPlugin;
 use crate::bullet::BulletPlugin;
 use crate::camera::CameraPlugin;
+use crate::controller::{Autopilot, ControllerPlugin};
 use crate::enemy::EnemiesPlugin;
 use crate::explosion::ExplosionsPlugin;
-use crate::physics::PhysicsPlugin;
-use crate::spaceship::SpaceshipPlugin;
+use crate::field::ForceFieldPlugin;
+use crate::missile::MissilePlugin;
+use crate::origin::FloatingOriginPlugin;
+use crate::physics::{Broadphase, Integrator, PhysicsPlugin};
+use crate::replay::{ReplayMode, ReplayPlugin};
+use crate::snapshot::SnapshotPlugin;
+use crate::spaceship::{ControlMode, SpaceshipPlugin};
 use crate::stars::StarsPlugin;
 
 fn main() {
@@ -24,13 +44,20 @@ fn main() {
         .insert_resource(ClearColor(Color::rgb(0., 0., 0.)))
         .add_plugins((
             StarsPlugin,
-            InputPlugin,
+            InputPlugin { scheme: ControlScheme::from_args() },
             CameraPlugin,
-            SpaceshipPlugin,
+            SpaceshipPlugin { players: PlayerSlot::count_from_args(), control_mode: ControlMode::from_args() },
             ExplosionsPlugin,
             EnemiesPlugin,
-            BulletPlugin,
-            PhysicsPlugin
+            BulletPlugin::default(),
+            BeamPlugin,
+            MissilePlugin,
+            PhysicsPlugin { integrator: Integrator::from_args(), broadphase: Broadphase::from_args() },
+            ForceFieldPlugin,
+            FloatingOriginPlugin,
+            ReplayPlugin(ReplayMode::from_args()),
+            SnapshotPlugin,
+            ControllerPlugin { autopilot: Autopilot::from_args() }
         ))
         .run();
 }
@@ -0,0 +1,122 @@
+use bevy::prelude::*;
+use serde::Deserialize;
+use crate::enemy::Enemy;
+use crate::physics::{PhysicsSet, Position, Rotation, Simulated, Velocity};
+
+pub struct MissilePlugin;
+
+impl Plugin for MissilePlugin {
+    fn build(&self, app: &mut App) {
+        // after the move, so the turn shows up in the interpolated rotation
+        app.add_systems(FixedUpdate, steer_missiles.after(PhysicsSet));
+    }
+}
+
+/// Steers a projectile towards the nearest enemy in front of it, picking a new one whenever its
+/// target is gone.
+#[derive(Component, Clone, Debug, Deserialize)]
+pub struct Homing {
+    /// How far ahead the missile looks for targets.
+    pub seek_range: f32,
+    /// Half the width of the cone, in radians, in which the missile looks for targets.
+    pub seek_angle: f32,
+    /// Radians per second.
+    pub turn_rate: f32,
+    #[serde(skip)]
+    pub target: Option<Entity>,
+}
+
+/// Damage dealt to every enemy within `radius` of where the projectile hits.
+#[derive(Component, Clone, Copy, Debug, Deserialize)]
+pub struct Blast {
+    pub radius: f32,
+    pub damage: f32,
+}
+
+impl Homing {
+    /// The nearest candidate within range and inside the seek cone around `heading`.
+    fn pick_target(&self, position: Vec2, heading: Vec2, candidates: impl Iterator<Item = (Entity, Vec2)>) -> Option<Entity> {
+        candidates
+            .filter(|(_, candidate)| {
+                let offset = *candidate - position;
+                offset.length() <= self.seek_range && heading.angle_between(offset).abs() <= self.seek_angle
+            })
+            // ties go to the lower entity, so replays pick the same target
+            .min_by(|(a, a_position), (b, b_position)| {
+                a_position.distance_squared(position)
+                    .total_cmp(&b_position.distance_squared(position))
+                    .then(a.cmp(b))
+            })
+            .map(|(entity, _)| entity)
+    }
+}
+
+/// `heading` turned towards `desired` by at most `max_angle` radians.
+fn turn_towards(heading: Vec2, desired: Vec2, max_angle: f32) -> Vec2 {
+    let angle = heading.angle_between(desired);
+    if angle.is_nan() {
+        return heading;
+    }
+    Vec2::from_angle(angle.clamp(-max_angle, max_angle)).rotate(heading)
+}
+
+fn steer_missiles(
+    mut q_missiles: Query<(&Position, &mut Velocity, &mut Rotation, &mut Homing, &Simulated)>,
+    q_enemies: Query<(Entity, &Position), With<Enemy>>,
+    time: Res<Time>
+) {
+    for (position, mut velocity, mut rotation, mut homing, simulated) in q_missiles.iter_mut() {
+        if !simulated.0 {
+            continue;
+        }
+        let speed = velocity.0.length();
+        let heading = velocity.0.normalize_or_zero();
+        if heading == Vec2::ZERO {
+            continue;
+        }
+        if !homing.target.is_some_and(|target| q_enemies.contains(target)) {
+            let candidates = q_enemies.iter().map(|(entity, enemy)| (entity, enemy.current));
+            homing.target = homing.pick_target(position.current, heading, candidates);
+        }
+        let Some((_, target)) = homing.target.and_then(|target| q_enemies.get(target).ok()) else { continue };
+        let heading = turn_towards(heading, target.current - position.current, homing.turn_rate * time.delta_seconds());
+        velocity.0 = heading * speed;
+        rotation.current = Vec2::Y.angle_between(heading);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+    use super::*;
+
+    fn homing() -> Homing {
+        Homing { seek_range: 500., seek_angle: 0.5, turn_rate: 2., target: None }
+    }
+
+    #[test]
+    fn picks_the_nearest_enemy_inside_the_cone() {
+        let (behind, far, near, wide) = (Entity::from_raw(1), Entity::from_raw(2), Entity::from_raw(3), Entity::from_raw(4));
+        let candidates = [
+            (behind, Vec2::new(0., -50.)),
+            (far, Vec2::new(10., 300.)),
+            (near, Vec2::new(-20., 200.)),
+            (wide, Vec2::new(100., 20.)),
+        ];
+        assert_eq!(homing().pick_target(Vec2::ZERO, Vec2::Y, candidates.into_iter()), Some(near));
+        assert_eq!(homing().pick_target(Vec2::ZERO, Vec2::Y, candidates.into_iter().filter(|(entity, _)| *entity != near)), Some(far));
+        let out_of_range = [(far, Vec2::new(0., 600.))];
+        assert_eq!(homing().pick_target(Vec2::ZERO, Vec2::Y, out_of_range.into_iter()), None);
+    }
+
+    #[test]
+    fn turns_no_faster_than_the_turn_rate() {
+        let turned = turn_towards(Vec2::Y, Vec2::X, 0.1);
+        assert!((Vec2::Y.angle_between(turned) + 0.1).abs() < 1e-5, "{turned}");
+        assert!((turned.length() - 1.).abs() < 1e-5);
+        // close enough to turn all the way
+        let turned = turn_towards(Vec2::Y, Vec2::from_angle(-0.05).rotate(Vec2::Y), 0.1);
+        assert!(turned.abs_diff_eq(Vec2::from_angle(-0.05).rotate(Vec2::Y), 1e-5));
+        assert!(turn_towards(Vec2::Y, -Vec2::X, FRAC_PI_2).abs_diff_eq(-Vec2::X, 1e-5));
+    }
+}
@@ -0,0 +1,128 @@
+use bevy::ecs::system::SystemParam;
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use crate::camera::VisibleSpace;
+use crate::input::{ActionBuffer, InputState};
+use crate::physics::Position;
+use crate::spaceship::{centroid, Spaceship};
+use crate::stars::{VisibleStarField, STARS_DENSITY};
+
+/// How far the players may get from the origin before the world is recentred on them. Well
+/// below the range where `f32` positions start to jitter.
+const RECENTER_DISTANCE: f32 = 10_000.;
+
+/// Keeps coordinates small however far the players fly, by moving the origin to them every
+/// now and then and shifting everything else the other way.
+pub struct FloatingOriginPlugin;
+
+impl Plugin for FloatingOriginPlugin {
+    fn build(&self, app: &mut App) {
+        app
+            .add_systems(Startup, setup_origin)
+            // after the whole tick, so every system within a tick sees the same origin
+            .add_systems(FixedLast, recenter_on_players);
+    }
+}
+
+/// Where the current origin lies in absolute world coordinates. Always a whole number of star
+/// cells, so the starfield lines up across a shift.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub struct FloatingOrigin(pub I64Vec2);
+
+fn setup_origin(mut commands: Commands) {
+    commands.spawn(FloatingOrigin::default());
+}
+
+fn recenter_on_players(mut params: ParamSet<(Query<&Position, With<Spaceship>>, OriginShift)>) {
+    let Some(centre) = centroid(params.p0().iter()) else { return };
+    if centre.abs().max_element() < RECENTER_DISTANCE {
+        return;
+    }
+    params.p1().recenter((centre / STARS_DENSITY).round().as_i64vec2() * STARS_DENSITY as i64);
+}
+
+/// Everything that holds a position in world space.
+#[derive(SystemParam)]
+pub struct OriginShift<'w, 's> {
+    origin: Query<'w, 's, &'static mut FloatingOrigin>,
+    positions: Query<'w, 's, &'static mut Position>,
+    // children move with their parent
+    transforms: Query<'w, 's, &'static mut Transform, Without<Parent>>,
+    visible_space: Query<'w, 's, &'static mut VisibleSpace>,
+    visible_star_field: Query<'w, 's, &'static mut VisibleStarField>,
+    inputs: Query<'w, 's, &'static mut InputState>,
+    action_buffers: Query<'w, 's, &'static mut ActionBuffer>,
+}
+
+impl OriginShift<'_, '_> {
+    /// Moves the origin to `offset`, given relative to the current origin, and everything in the
+    /// world the other way. Previous positions move too, so interpolation carries on seamlessly.
+    pub fn recenter(&mut self, offset: I64Vec2) {
+        self.origin.single_mut().0 += offset;
+        let shift = offset.as_vec2();
+        for mut position in self.positions.iter_mut() {
+            position.current -= shift;
+            position.previous -= shift;
+        }
+        for mut transform in self.transforms.iter_mut() {
+            transform.translation -= shift.extend(0.);
+        }
+        for mut visible_space in self.visible_space.iter_mut() {
+            visible_space.top_left -= shift;
+            visible_space.bottom_right -= shift;
+        }
+        for mut visible_star_field in self.visible_star_field.iter_mut() {
+            visible_star_field.top_left -= shift;
+            visible_star_field.bottom_right -= shift;
+        }
+        for mut input_state in self.inputs.iter_mut() {
+            if let Some(aim) = &mut input_state.aim {
+                *aim -= shift;
+            }
+        }
+        for mut action_buffer in self.action_buffers.iter_mut() {
+            action_buffer.shift_aim(shift);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use crate::stars::StarKey;
+    use super::*;
+
+    #[test]
+    fn recentering_keeps_everything_in_place_relative_to_the_player() {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, FloatingOriginPlugin));
+        let far = Vec2::new(25_013., -40_007.);
+        let ship = app.world.spawn((
+            Spaceship,
+            Position { current: far, previous: far - Vec2::new(3., 1.) },
+            TransformBundle::from_transform(Transform::from_translation(far.extend(0.))),
+        )).id();
+        let star_position = far + Vec2::new(100., 50.);
+        let star = app.world.spawn((
+            StarKey((834, -1332)),
+            TransformBundle::from_transform(Transform::from_translation(star_position.extend(0.))),
+        )).id();
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.update();
+
+        let origin = app.world.query::<&FloatingOrigin>().single(&app.world).0;
+        assert_eq!(origin, I64Vec2::new(25_020, -40_020));
+        let position = *app.world.get::<Position>(ship).unwrap();
+        assert!(position.current.length() < STARS_DENSITY);
+        assert_eq!(position.current - position.previous, Vec2::new(3., 1.));
+        assert_eq!(position.current + origin.as_vec2(), far);
+        let star_translation = app.world.get::<Transform>(star).unwrap().translation.truncate();
+        assert_eq!(star_translation - position.current, Vec2::new(100., 50.));
+        assert_eq!(app.world.get::<StarKey>(star).unwrap().0, (834, -1332));
+    }
+}
@@ -1,66 +1,506 @@
+use std::f32::consts::{PI, TAU};
+use bevy::ecs::system::SystemParam;
 use bevy::prelude::*;
 use bevy::math::Vec2;
-use crate::bullet::Bullet;
-use crate::enemy::{Collider, Damage, Enemy};
+use crate::broadphase::{SpatialHash, CELL_SIZE};
+use crate::collider::{CollisionLayers, Colliders, Contact, RayHit};
+use crate::field::ForceField;
 
-pub struct PhysicsPlugin;
+#[derive(Default)]
+pub struct PhysicsPlugin {
+    pub integrator: Integrator,
+    pub broadphase: Broadphase,
+}
 
 impl Plugin for PhysicsPlugin {
     fn build(&self, app: &mut App) {
         app
-            .add_systems(FixedUpdate, (update_positions, handle_collisions))
+            .insert_resource(self.integrator)
+            .insert_resource(self.broadphase)
+            .add_event::<CollisionEvent>()
+            .add_event::<ContactEvent>()
+            .add_systems(Startup, setup_spatial_hash)
+            .add_systems(
+                FixedUpdate,
+                (update_positions, update_rotations, rebuild_spatial_hash, detect_collisions, resolve_contacts)
+                    .chain()
+                    .in_set(PhysicsSet)
+            )
             .add_systems(Update, interpolate);
     }
 }
 
+fn setup_spatial_hash(mut commands: Commands) {
+    commands.spawn(SpatialHash::new(CELL_SIZE));
+}
+
 fn interpolate(
-    mut query: Query<(&mut Transform, &Position), With<Position>>,
+    mut query: Query<(&mut Transform, &Position, Option<&Rotation>), With<Position>>,
     time: Res<Time<Fixed>>,
     // time_unfixed: Res<Time>
 ) {
     let overstep = time.overstep_fraction();
-    for (mut transform, position) in &mut query {
+    for (mut transform, position, rotation) in &mut query {
         transform.translation.x = position.previous.x + (position.current.x - position.previous.x) * overstep;
         transform.translation.y = position.previous.y + (position.current.y - position.previous.y) * overstep;
+        if let Some(rotation) = rotation {
+            transform.rotation = Quat::from_rotation_z(rotation.interpolated(overstep));
+        }
     }
     // dbg!(1. / time_unfixed.delta_seconds());
 }
 
 fn update_positions
 (
-    mut query: Query<(&mut Position, &Velocity)>,
+    mut queries: ParamSet<(
+        Query<(&ForceField, &Position)>,
+        Query<(
+            &mut Position,
+            &mut Velocity,
+            Option<&Mass>,
+            Option<&mut Force>,
+            Option<&mut Impulse>,
+            Option<&LinearDamping>,
+            Option<&MaxSpeed>,
+            Option<&Simulated>
+        )>
+    )>,
+    integrator: Res<Integrator>,
     time: Res<Time<Fixed>>
 ) {
-    for (mut position, velocity) in &mut query {
+    let fields: Vec<(ForceField, Vec2)> = queries.p0().iter().map(|(field, position)| (*field, position.current)).collect();
+    for (mut position, mut velocity, mass, force, impulse, damping, max_speed, simulated) in &mut queries.p1() {
+        if simulated.is_some_and(|simulated| !simulated.0) {
+            continue;
+        }
+        let mass = mass.map_or(1., |mass| mass.0);
+        if let Some(mut impulse) = impulse {
+            velocity.0 += impulse.0 / mass;
+            impulse.0 = Vec2::ZERO;
+        }
+        let mut acceleration = force.map_or(Vec2::ZERO, |mut force| std::mem::take(&mut force.0) / mass);
+        if mass.is_finite() {
+            acceleration += fields.iter()
+                .map(|(field, centre)| field.acceleration(*centre, position.current))
+                .sum::<Vec2>();
+        }
+        let body = Body {
+            damping: damping.map_or(0., |damping| damping.0),
+            max_speed: max_speed.map_or(f32::INFINITY, |max_speed| max_speed.0),
+        };
         position.previous = position.current;
-        position.current.x += velocity.0.x * time.delta_seconds();
-        position.current.y += velocity.0.y * time.delta_seconds();
+        integrator.step(&mut position.current, &mut velocity.0, acceleration, body, time.delta_seconds());
     }
 }
 
-fn handle_collisions
+fn update_rotations
 (
-    mut commands: Commands,
-    mut q_bullets: Query<(Entity, &Position), With<Bullet>>,
-    mut e_damage: EventWriter<Damage>,
-    q_enemies: Query<(Entity, &Position, &Collider), With<Enemy>>
+    mut query: Query<(&mut Rotation, Option<&AngularVelocity>, Option<&Simulated>)>,
+    time: Res<Time<Fixed>>
 ) {
-    for (enemy_entity, enemy_position, enemy_collider) in q_enemies.iter() {
-        for (bullet_ent, bullet_pos) in q_bullets.iter_mut() {
-            if (bullet_pos.current - enemy_position.current).length_squared() <= enemy_collider.0.radius.powi(2) {
-                // TODO despawning twice bug? processing collision over and over
-                commands.entity(bullet_ent).despawn();
-                e_damage.send(Damage(enemy_entity, 100.));
+    for (mut rotation, angular_velocity, simulated) in &mut query {
+        if simulated.is_some_and(|simulated| !simulated.0) {
+            continue;
+        }
+        rotation.previous = rotation.current;
+        if let Some(angular_velocity) = angular_velocity {
+            rotation.current += angular_velocity.0 * time.delta_seconds();
+        }
+    }
+}
+
+fn rebuild_spatial_hash
+(
+    mut q_spatial_hash: Query<&mut SpatialHash>,
+    colliders: Colliders
+) {
+    let mut spatial_hash = q_spatial_hash.single_mut();
+    spatial_hash.clear();
+    for (entity, collider) in colliders.iter() {
+        let (min, max) = collider.bounds();
+        spatial_hash.insert(entity, min, max);
+    }
+}
+
+fn detect_collisions
+(
+    q_spatial_hash: Query<&SpatialHash>,
+    q_layers: Query<&CollisionLayers>,
+    colliders: Colliders,
+    broadphase: Res<Broadphase>,
+    mut e_collisions: EventWriter<CollisionEvent>
+) {
+    let spatial_hash = q_spatial_hash.single();
+    // in entity order rather than storage order, which changes when a snapshot respawns bodies,
+    // so contacts are resolved in the same order on every run
+    let mut placed: Vec<_> = colliders.iter().collect();
+    placed.sort_unstable_by_key(|(entity, _)| *entity);
+    for (index, (entity, collider)) in placed.iter().enumerate() {
+        let layers = q_layers.get(*entity).copied().unwrap_or_default();
+        let mut test = |other: Entity| {
+            if !layers.interacts(&q_layers.get(other).copied().unwrap_or_default()) {
+                return;
+            }
+            let Some(other_collider) = colliders.get(other) else { return };
+            if let Some(contact) = collider.contact(&other_collider) {
+                e_collisions.send(CollisionEvent(*entity, other, contact));
             }
+        };
+        match *broadphase {
+            Broadphase::SpatialHash => {
+                let (min, max) = collider.bounds();
+                // both entities of a pair find each other, keep the pair only once
+                spatial_hash.query(min, max).into_iter().filter(|other| other > entity).for_each(test);
+            }
+            Broadphase::Naive => placed[index + 1..].iter().for_each(|(other, _)| test(*other)),
+        }
+    }
+}
+
+/// Casts rays against every collider, through the spatial hash built this tick. Systems using it
+/// run after [`PhysicsSet`].
+#[derive(SystemParam)]
+pub struct Raycast<'w, 's> {
+    spatial_hash: Query<'w, 's, &'static SpatialHash>,
+    layers: Query<'w, 's, &'static CollisionLayers>,
+    colliders: Colliders<'w, 's>,
+}
+
+impl Raycast<'_, '_> {
+    /// Every collider a ray from `origin` along the unit vector `direction` meets within
+    /// `max_distance`, nearest first. Layers work as between two colliders, with the ray on
+    /// `layers`.
+    pub fn cast(&self, origin: Vec2, direction: Vec2, max_distance: f32, layers: CollisionLayers) -> Vec<(Entity, RayHit)> {
+        let Ok(spatial_hash) = self.spatial_hash.get_single() else { return Vec::new() };
+        let end = origin + direction * max_distance;
+        let mut hits: Vec<(Entity, RayHit)> = spatial_hash
+            .query(origin.min(end), origin.max(end))
+            .into_iter()
+            .filter(|entity| layers.interacts(&self.layers.get(*entity).copied().unwrap_or_default()))
+            .filter_map(|entity| {
+                let placed = self.colliders.get(entity)?;
+                Some((entity, placed.collider.raycast(placed.pose, origin, direction, max_distance)?))
+            })
+            .collect();
+        // ties go to the lower entity, so replays agree on which is first
+        hits.sort_by(|(a, a_hit), (b, b_hit)| a_hit.distance.total_cmp(&b_hit.distance).then(a.cmp(b)));
+        hits
+    }
+
+    /// The nearest collider along the ray, see [`Raycast::cast`].
+    pub fn first(&self, origin: Vec2, direction: Vec2, max_distance: f32, layers: CollisionLayers) -> Option<(Entity, RayHit)> {
+        self.cast(origin, direction, max_distance, layers).into_iter().next()
+    }
+}
+
+/// Pushes touching rigid bodies apart and bounces them off each other.
+fn resolve_contacts
+(
+    mut collisions: EventReader<CollisionEvent>,
+    mut bodies: Query<(&mut Position, &mut Velocity, Option<&Mass>, &RigidBody)>,
+    mut e_contacts: EventWriter<ContactEvent>
+) {
+    for CollisionEvent(a, b, contact) in collisions.read() {
+        let Ok([a_body, b_body]) = bodies.get_many_mut([*a, *b]) else { continue };
+        let (mut a_position, mut a_velocity, a_mass, a_body) = a_body;
+        let (mut b_position, mut b_velocity, b_mass, b_body) = b_body;
+        let a_inverse_mass = 1. / a_mass.map_or(1., |mass| mass.0);
+        let b_inverse_mass = 1. / b_mass.map_or(1., |mass| mass.0);
+        let inverse_mass = a_inverse_mass + b_inverse_mass;
+        if inverse_mass <= 0. {
+            continue;
+        }
+
+        // the lighter body moves further
+        let separation = contact.normal * contact.depth.max(0.) / inverse_mass;
+        a_position.current -= separation * a_inverse_mass;
+        b_position.current += separation * b_inverse_mass;
+
+        let closing_speed = (a_velocity.0 - b_velocity.0).dot(contact.normal);
+        if closing_speed <= 0. {
+            continue;
         }
+        let restitution = a_body.restitution.max(b_body.restitution);
+        let impulse = contact.normal * (1. + restitution) * closing_speed / inverse_mass;
+        a_velocity.0 -= impulse * a_inverse_mass;
+        b_velocity.0 += impulse * b_inverse_mass;
+        e_contacts.send(ContactEvent { a: *a, b: *b, impulse });
     }
 }
 
-#[derive(Component)]
+/// Two colliders touching this tick, in no particular order, and where they first touched.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct CollisionEvent(pub Entity, pub Entity, pub Contact);
+
+impl CollisionEvent {
+    /// The pair ordered so the first entity matches `first`, if either does, with the contact
+    /// seen from that entity.
+    pub fn ordered(&self, first: impl Fn(Entity) -> bool) -> Option<(Entity, Entity, Contact)> {
+        if first(self.0) {
+            Some((self.0, self.1, self.2))
+        } else if first(self.1) {
+            Some((self.1, self.0, self.2.flipped()))
+        } else {
+            None
+        }
+    }
+}
+
+/// Momentum exchanged between two rigid bodies that bumped into each other. `impulse` is what
+/// `b` received; `a` received the opposite.
+#[derive(Event, Clone, Copy, Debug)]
+pub struct ContactEvent {
+    pub a: Entity,
+    pub b: Entity,
+    pub impulse: Vec2,
+}
+
+/// Position integration and collision detection. Systems reacting to [`CollisionEvent`]s run after it.
+#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
+pub struct PhysicsSet;
+
+#[derive(Component, Clone, Copy)]
 pub struct Velocity(pub Vec2);
 
+/// Whether physics moves and collides the entity; those without it always take part. Switched off
+/// on entities parked for reuse, which is cheaper than taking their components away.
+#[derive(Component, Clone, Copy)]
+pub struct Simulated(pub bool);
+
+/// Orientation in radians, counter-clockwise from the sprite's own orientation. Like
+/// [`Position`] it keeps the previous tick's value for interpolation.
+#[derive(Component, Copy, Clone, Default)]
+pub struct Rotation {
+    pub current: f32,
+    pub previous: f32
+}
+
+impl Rotation {
+    pub fn new(angle: f32) -> Self {
+        Rotation { current: angle, previous: angle }
+    }
+
+    /// The angle `fraction` of the way from the previous tick, turning the short way round.
+    pub fn interpolated(&self, fraction: f32) -> f32 {
+        let turn = (self.current - self.previous + PI).rem_euclid(TAU) - PI;
+        self.previous + turn * fraction
+    }
+}
+
+/// Turn rate in radians per second, counter-clockwise.
+#[derive(Component, Clone, Copy, Default)]
+pub struct AngularVelocity(pub f32);
+
+/// Makes an entity bounce off other rigid bodies it collides with instead of passing through.
+#[derive(Component, Clone, Copy)]
+pub struct RigidBody {
+    /// Share of the closing speed kept after a bounce, from 0 (dead stop) to 1 (elastic).
+    /// The bouncier of the two bodies decides.
+    pub restitution: f32,
+}
+
+/// Entities without a mass weigh 1. An infinite mass never moves when bumped. A mass of zero
+/// or less would send a body off at infinite speed, so there is none.
+#[derive(Component, Clone, Copy)]
+pub struct Mass(f32);
+
+impl Mass {
+    /// Panics unless `mass` is above zero.
+    pub fn new(mass: f32) -> Self {
+        assert!(mass > 0., "mass must be above zero, got {mass}");
+        Mass(mass)
+    }
+
+    pub fn get(self) -> f32 {
+        self.0
+    }
+}
+
+/// Force to apply over the next tick. Cleared once applied, so systems add to it every tick.
+#[derive(Component, Clone, Copy, Default)]
+pub struct Force(pub Vec2);
+
+/// Instant change of momentum, applied once at the start of the next tick.
+#[derive(Component, Clone, Copy, Default)]
+pub struct Impulse(pub Vec2);
+
+/// Fraction of velocity lost per second, like drag.
+#[derive(Component, Clone, Copy)]
+pub struct LinearDamping(pub f32);
+
+#[derive(Component, Clone, Copy)]
+pub struct MaxSpeed(pub f32);
+
+/// How velocity and position are advanced each fixed tick.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Integrator {
+    /// Updates velocity first, then moves with the new velocity. Cheap and stable.
+    #[default]
+    SemiImplicitEuler,
+    /// Velocity Verlet. Moves along the exact curve for a force held over the tick, so paths
+    /// under thrust don't drift with the tick rate.
+    VelocityVerlet,
+}
+
+/// How colliders that may touch are found before testing them exactly.
+#[derive(Resource, Clone, Copy, Debug, Default, PartialEq)]
+pub enum Broadphase {
+    /// Only tests colliders whose boxes share a [`SpatialHash`] cell.
+    #[default]
+    SpatialHash,
+    /// Tests every pair of colliders. The baseline the spatial hash is checked and measured against.
+    Naive,
+}
+
+impl Broadphase {
+    /// Reads `--naive-broadphase` from the command line.
+    pub fn from_args() -> Self {
+        if std::env::args().skip(1).any(|arg| arg == "--naive-broadphase") {
+            Broadphase::Naive
+        } else {
+            Broadphase::SpatialHash
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+struct Body {
+    damping: f32,
+    max_speed: f32,
+}
+
+impl Integrator {
+    /// Reads `--verlet` from the command line.
+    pub fn from_args() -> Self {
+        if std::env::args().skip(1).any(|arg| arg == "--verlet") {
+            Integrator::VelocityVerlet
+        } else {
+            Integrator::SemiImplicitEuler
+        }
+    }
+
+    fn step(self, position: &mut Vec2, velocity: &mut Vec2, acceleration: Vec2, body: Body, dt: f32) {
+        let start_velocity = *velocity;
+        // implicit damping never reverses the velocity, however large the step
+        *velocity = ((*velocity + acceleration * dt) / (1. + body.damping * dt)).clamp_length_max(body.max_speed);
+        *position += match self {
+            Integrator::SemiImplicitEuler => *velocity * dt,
+            Integrator::VelocityVerlet => (start_velocity + *velocity) / 2. * dt,
+        };
+    }
+}
+
 #[derive(Component, Copy, Clone)]
 pub struct Position {
     pub current: Vec2,
     pub previous: Vec2
 }
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use crate::collider::Collider;
+    use super::*;
+
+    const FREE: Body = Body { damping: 0., max_speed: f32::INFINITY };
+
+    /// Position after `seconds` of constant acceleration from rest.
+    fn travel(integrator: Integrator, acceleration: Vec2, body: Body, seconds: f32, ticks: u32) -> (Vec2, Vec2) {
+        let (mut position, mut velocity) = (Vec2::ZERO, Vec2::ZERO);
+        for _ in 0..ticks {
+            integrator.step(&mut position, &mut velocity, acceleration, body, seconds / ticks as f32);
+        }
+        (position, velocity)
+    }
+
+    #[test]
+    fn verlet_is_exact_under_constant_force() {
+        let exact = Vec2::new(0., 50.);
+        for ticks in [4, 64] {
+            let (position, velocity) = travel(Integrator::VelocityVerlet, Vec2::Y * 100., FREE, 1., ticks);
+            assert!(position.abs_diff_eq(exact, 1e-3), "{ticks} ticks: {position}");
+            assert!(velocity.abs_diff_eq(Vec2::Y * 100., 1e-3));
+        }
+        // semi-implicit Euler overshoots by half a tick's worth of speed
+        let (position, _) = travel(Integrator::SemiImplicitEuler, Vec2::Y * 100., FREE, 1., 4);
+        assert!(position.abs_diff_eq(Vec2::new(0., 62.5), 1e-3), "{position}");
+    }
+
+    #[test]
+    fn rotation_interpolates_the_short_way_round() {
+        let rotation = Rotation { previous: 0.1, current: 0.3 };
+        assert!((rotation.interpolated(0.5) - 0.2).abs() < 1e-6);
+        // wrapped from just below a full turn to just above zero: 0.2 forwards, not 6 backwards
+        let rotation = Rotation { previous: TAU - 0.1, current: 0.1 };
+        assert!((rotation.interpolated(0.5) - TAU).abs() < 1e-5);
+    }
+
+    fn app_with_bodies(bodies: [(Vec2, Vec2, f32); 2]) -> (App, [Entity; 2]) {
+        let mut app = App::new();
+        app.add_plugins((MinimalPlugins, PhysicsPlugin::default()));
+        let entities = bodies.map(|(position, velocity, mass)| {
+            app.world.spawn((
+                TransformBundle::default(),
+                Position { current: position, previous: position },
+                Velocity(velocity),
+                Mass::new(mass),
+                RigidBody { restitution: 1. },
+                Collider::Circle(Circle::new(10.)),
+            )).id()
+        });
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        (app, entities)
+    }
+
+    fn tick(app: &mut App) {
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app.update();
+    }
+
+    #[test]
+    fn equal_bodies_swap_velocities_in_an_elastic_bump() {
+        let (mut app, [a, b]) = app_with_bodies([(Vec2::ZERO, Vec2::X * 100., 1.), (Vec2::new(19., 0.), Vec2::ZERO, 1.)]);
+        tick(&mut app);
+        assert!(app.world.get::<Velocity>(a).unwrap().0.abs_diff_eq(Vec2::ZERO, 1e-3));
+        assert!(app.world.get::<Velocity>(b).unwrap().0.abs_diff_eq(Vec2::X * 100., 1e-3));
+        let events = app.world.resource::<Events<ContactEvent>>();
+        let contact = events.iter_current_update_events().next().unwrap();
+        assert!(contact.impulse.abs_diff_eq(Vec2::X * 100. * if contact.b == b { 1. } else { -1. }, 1e-3));
+        // pushed apart until they just touch
+        let gap = app.world.get::<Position>(b).unwrap().current.x - app.world.get::<Position>(a).unwrap().current.x;
+        assert!((gap - 20.).abs() < 1e-3, "{gap}");
+    }
+
+    #[test]
+    fn infinite_mass_does_not_move() {
+        let (mut app, [ship, wall]) = app_with_bodies([(Vec2::ZERO, Vec2::X * 100., 1.), (Vec2::new(19., 0.), Vec2::ZERO, f32::INFINITY)]);
+        tick(&mut app);
+        assert!(app.world.get::<Velocity>(ship).unwrap().0.abs_diff_eq(Vec2::X * -100., 1e-3));
+        assert_eq!(app.world.get::<Velocity>(wall).unwrap().0, Vec2::ZERO);
+        assert_eq!(app.world.get::<Position>(wall).unwrap().current, Vec2::new(19., 0.));
+    }
+
+    #[test]
+    #[should_panic(expected = "mass must be above zero")]
+    fn massless_bodies_are_refused() {
+        Mass::new(0.);
+    }
+
+    #[test]
+    fn damping_slows_without_reversing() {
+        let body = Body { damping: 1000., max_speed: f32::INFINITY };
+        let (mut position, mut velocity) = (Vec2::ZERO, Vec2::X * 100.);
+        Integrator::SemiImplicitEuler.step(&mut position, &mut velocity, Vec2::ZERO, body, 1. / 64.);
+        assert!(velocity.x > 0. && velocity.x < 10., "{velocity}");
+    }
+
+    #[test]
+    fn speed_is_capped_in_every_direction() {
+        let body = Body { damping: 0., max_speed: 400. };
+        let (_, velocity) = travel(Integrator::SemiImplicitEuler, Vec2::new(2500., 2500.), body, 1., 64);
+        assert!((velocity.length() - 400.).abs() < 1e-3, "{velocity}");
+    }
+}
@@ -0,0 +1,140 @@
+use std::collections::VecDeque;
+use std::fs::{self, File};
+use std::io::Write;
+use std::path::PathBuf;
+use bevy::prelude::*;
+use rand::{Rng, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use crate::controller::ControllerSet;
+use crate::input::{InputSet, InputState, PlayerSlot};
+
+/// How the player's input is sourced for this run.
+///
+/// A recording is a RON header line holding the RNG seed followed by one
+/// line per `FixedUpdate` tick listing every player's `InputState` for that tick,
+/// ordered by player slot.
+#[derive(Clone, Debug)]
+pub enum ReplayMode {
+    Live,
+    Record(PathBuf),
+    Replay(PathBuf),
+}
+
+impl ReplayMode {
+    /// Reads `--record <file>` or `--replay <file>` from the command line.
+    pub fn from_args() -> Self {
+        let mut args = std::env::args().skip(1);
+        while let Some(arg) = args.next() {
+            match arg.as_str() {
+                "--record" => return args.next().map_or(ReplayMode::Live, |path| ReplayMode::Record(path.into())),
+                "--replay" => return args.next().map_or(ReplayMode::Live, |path| ReplayMode::Replay(path.into())),
+                _ => {}
+            }
+        }
+        ReplayMode::Live
+    }
+}
+
+pub struct ReplayPlugin(pub ReplayMode);
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match self.0.clone() {
+            ReplayMode::Live => {
+                app.add_systems(Startup, move |commands: Commands| spawn_rng(commands, rand::thread_rng().gen()));
+            }
+            ReplayMode::Record(path) => {
+                app
+                    .add_systems(Startup, move |commands: Commands| start_recording(commands, &path))
+                    .add_systems(FixedPreUpdate, record_input.after(InputSet).after(ControllerSet));
+            }
+            ReplayMode::Replay(path) => {
+                app
+                    .configure_sets(Update, InputSet.run_if(|| false))
+                    .configure_sets(FixedPreUpdate, InputSet.run_if(|| false))
+                    .add_systems(Startup, move |commands: Commands| start_playback(commands, &path))
+                    .add_systems(FixedPreUpdate, play_back_input.after(ControllerSet));
+            }
+        }
+    }
+}
+
+/// Source of all gameplay randomness, so a replay draws the same numbers as the recorded run.
+#[derive(Component)]
+pub struct GameRng(pub ChaCha8Rng);
+
+#[derive(Serialize, Deserialize)]
+struct ReplayHeader {
+    seed: u64,
+}
+
+#[derive(Component)]
+struct Recorder(File);
+
+#[derive(Component)]
+struct Playback(VecDeque<Vec<InputState>>);
+
+fn spawn_rng(mut commands: Commands, seed: u64) {
+    commands.spawn(GameRng(ChaCha8Rng::seed_from_u64(seed)));
+}
+
+fn start_recording(mut commands: Commands, path: &PathBuf) {
+    let seed = rand::thread_rng().gen();
+    let header = ron::to_string(&ReplayHeader { seed }).expect("replay header serializes");
+    match File::create(path).and_then(|mut file| writeln!(file, "{header}").map(|_| file)) {
+        Ok(file) => {
+            commands.spawn(Recorder(file));
+        }
+        Err(err) => error!("could not create recording {} ({err})", path.display()),
+    }
+    spawn_rng(commands, seed);
+}
+
+fn record_input(
+    mut commands: Commands,
+    mut recorder: Query<(Entity, &mut Recorder)>,
+    input_states: Query<(&InputState, &PlayerSlot)>
+) {
+    let Ok((entity, mut recorder)) = recorder.get_single_mut() else { return };
+    let mut players: Vec<_> = input_states.iter().collect();
+    players.sort_by_key(|(_, slot)| **slot);
+    let frame: Vec<&InputState> = players.into_iter().map(|(input_state, _)| input_state).collect();
+    let frame = ron::to_string(&frame).expect("input state serializes");
+    if let Err(err) = writeln!(recorder.0, "{frame}") {
+        error!("stopped recording ({err})");
+        commands.entity(entity).despawn();
+    }
+}
+
+fn start_playback(mut commands: Commands, path: &PathBuf) {
+    let contents = fs::read_to_string(path)
+        .unwrap_or_else(|err| panic!("could not read recording {} ({err})", path.display()));
+    let mut lines = contents.lines();
+    let header: ReplayHeader = lines.next()
+        .and_then(|line| ron::from_str(line).ok())
+        .unwrap_or_else(|| panic!("recording {} has no valid header", path.display()));
+    let frames = lines
+        .map(|line| ron::from_str(line).unwrap_or_else(|err| panic!("corrupt frame in {} ({err})", path.display())))
+        .collect();
+    commands.spawn(Playback(frames));
+    spawn_rng(commands, header.seed);
+}
+
+fn play_back_input(
+    mut commands: Commands,
+    mut playback: Query<(Entity, &mut Playback)>,
+    mut input_states: Query<(&mut InputState, &PlayerSlot)>
+) {
+    let Ok((entity, mut playback)) = playback.get_single_mut() else { return };
+    let frame = playback.0.pop_front();
+    if frame.is_none() {
+        info!("replay finished");
+        commands.entity(entity).despawn();
+    }
+    for (mut input_state, slot) in input_states.iter_mut() {
+        *input_state = frame.as_ref()
+            .and_then(|frame| frame.get(slot.0).cloned())
+            .unwrap_or_default();
+    }
+}
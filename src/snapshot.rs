@@ -0,0 +1,343 @@
+use std::marker::PhantomData;
+use bevy::ecs::system::RunSystemOnce;
+use bevy::math::I64Vec2;
+use bevy::prelude::*;
+use bevy::render::primitives::Aabb;
+use bevy::utils::HashSet;
+use rand_chacha::ChaCha8Rng;
+use crate::bullet::{Bullet, BulletTimer, Pierce, ProjectilePool, Ricochet, Split};
+use crate::camera::{Camera, CameraData, VisibleSpace};
+use crate::collider::{Collider, CollisionLayers, ContinuousCollision};
+use crate::enemy::{Enemy, Health};
+use crate::field::ForceField;
+use crate::flight::FlightModel;
+use crate::input::PlayerSlot;
+use crate::missile::{Blast, Homing};
+use crate::origin::{FloatingOrigin, OriginShift};
+use crate::physics::{
+    AngularVelocity, Force, Impulse, LinearDamping, Mass, MaxSpeed, Position, RigidBody, Rotation, Simulated, Velocity
+};
+use crate::replay::GameRng;
+use crate::spaceship::{ControlMode, GunTimer, Spaceship, SpaceshipState};
+use crate::weapon::{Loadout, Weapon};
+
+/// F5 snapshots the simulation and F9 rewinds to that snapshot, for stepping back to a moment
+/// while debugging.
+pub struct SnapshotPlugin;
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(Update, quick_save_and_load);
+    }
+}
+
+/// The simulation as it was at the end of a fixed tick: every entity with a `Velocity`, the
+/// camera, the game RNG, the floating origin and which projectiles are waiting to be reused.
+///
+/// Restoring despawns whatever was spawned since and respawns whatever was destroyed, under the
+/// same `Entity` where possible. Running the same ticks again with the same input then gives
+/// bit-identical results, which is what rollback needs. Effects are left alone.
+pub struct WorldSnapshot {
+    entities: Vec<SavedEntity>,
+    rng: Option<ChaCha8Rng>,
+    origin: Option<FloatingOrigin>,
+    pool: Option<ProjectilePool>,
+    visible_space: Option<VisibleSpace>,
+}
+
+struct SavedEntity {
+    /// Updated when a restore cannot reuse the id, so the snapshot can be restored again.
+    entity: Entity,
+    components: Vec<Box<dyn SavedComponent>>,
+}
+
+trait SavedComponent: Send + Sync {
+    fn restore_into(&self, entity: &mut EntityWorldMut);
+}
+
+impl<C: Component + Clone> SavedComponent for C {
+    fn restore_into(&self, entity: &mut EntityWorldMut) {
+        entity.insert(self.clone());
+    }
+}
+
+/// A component the entity did not have, removed on restore in case it was added since.
+struct Absent<C>(PhantomData<C>);
+
+impl<C: Component> SavedComponent for Absent<C> {
+    fn restore_into(&self, entity: &mut EntityWorldMut) {
+        entity.remove::<C>();
+    }
+}
+
+impl WorldSnapshot {
+    /// Snapshots the world. Call between fixed ticks.
+    pub fn take(world: &mut World) -> Self {
+        // the camera culls projectiles
+        let entities = world
+            .query_filtered::<EntityRef, Or<(With<Velocity>, With<Camera>)>>()
+            .iter(world)
+            .map(|entity| SavedEntity { entity: entity.id(), components: save_components(entity) })
+            .collect();
+        let rng = world.query::<&GameRng>().get_single(world).ok().map(|rng| rng.0.clone());
+        let origin = world.query::<&FloatingOrigin>().get_single(world).ok().copied();
+        let pool = world.query::<&ProjectilePool>().get_single(world).ok().cloned();
+        let visible_space = world.query::<&VisibleSpace>().get_single(world).ok().cloned();
+        WorldSnapshot { entities, rng, origin, pool, visible_space }
+    }
+
+    /// Puts the world back the way it was when the snapshot was taken.
+    pub fn restore(&mut self, world: &mut World) {
+        // move the origin back first, so the stars and camera line up with the restored bodies
+        if let (Some(saved), Ok(current)) = (self.origin, world.query::<&FloatingOrigin>().get_single(world)) {
+            let offset = saved.0 - current.0;
+            if offset != I64Vec2::ZERO {
+                world.run_system_once_with(offset, |In(offset), mut world: OriginShift| world.recenter(offset));
+            }
+        }
+
+        let saved: HashSet<Entity> = self.entities.iter().map(|saved| saved.entity).collect();
+        let spawned_since: Vec<Entity> = world
+            .query_filtered::<Entity, With<Velocity>>()
+            .iter(world)
+            .filter(|entity| !saved.contains(entity))
+            .collect();
+        for entity in spawned_since {
+            world.entity_mut(entity).despawn_recursive();
+        }
+
+        let mut pool = self.pool.clone();
+        for saved in &mut self.entities {
+            let mut entity = match world.get_or_spawn(saved.entity) {
+                Some(entity) => entity,
+                // the id went to something that is still around
+                None => world.spawn_empty(),
+            };
+            if let Some(pool) = &mut pool {
+                pool.replace(saved.entity, entity.id());
+            }
+            saved.entity = entity.id();
+            for component in &saved.components {
+                component.restore_into(&mut entity);
+            }
+        }
+
+        if let Some(pool) = &pool {
+            for mut current in world.query::<&mut ProjectilePool>().iter_mut(world) {
+                current.clone_from(pool);
+            }
+        }
+        self.pool = pool;
+        if let Some(visible_space) = &self.visible_space {
+            for mut current in world.query::<&mut VisibleSpace>().iter_mut(world) {
+                current.clone_from(visible_space);
+            }
+        }
+        if let Some(rng) = &self.rng {
+            for mut game_rng in world.query::<&mut GameRng>().iter_mut(world) {
+                game_rng.0 = rng.clone();
+            }
+        }
+    }
+}
+
+/// Everything needed to put the entity back, including its sprite in case it has to be respawned.
+/// A component missing here is lost when its entity is respawned. One listed here but missing
+/// from the entity is removed on restore, since a reused projectile may have picked it up since.
+fn save_components(entity: EntityRef) -> Vec<Box<dyn SavedComponent>> {
+    let mut components = Vec::new();
+    let mut save = |component: Box<dyn SavedComponent>| components.push(component);
+    fn boxed<C: Component + Clone>(entity: EntityRef) -> Box<dyn SavedComponent> {
+        match entity.get::<C>() {
+            Some(component) => Box::new(component.clone()),
+            None => Box::new(Absent::<C>(PhantomData)),
+        }
+    }
+
+    save(boxed::<Position>(entity));
+    save(boxed::<Rotation>(entity));
+    save(boxed::<Velocity>(entity));
+    save(boxed::<Simulated>(entity));
+    save(boxed::<AngularVelocity>(entity));
+    save(boxed::<Force>(entity));
+    save(boxed::<Impulse>(entity));
+    save(boxed::<Mass>(entity));
+    save(boxed::<LinearDamping>(entity));
+    save(boxed::<MaxSpeed>(entity));
+    save(boxed::<RigidBody>(entity));
+    save(boxed::<Collider>(entity));
+    save(boxed::<CollisionLayers>(entity));
+    save(boxed::<ContinuousCollision>(entity));
+    save(boxed::<ForceField>(entity));
+
+    save(boxed::<Enemy>(entity));
+    save(boxed::<Health>(entity));
+    save(boxed::<Bullet>(entity));
+    save(boxed::<BulletTimer>(entity));
+    save(boxed::<Homing>(entity));
+    save(boxed::<Blast>(entity));
+    save(boxed::<Pierce>(entity));
+    save(boxed::<Ricochet>(entity));
+    save(boxed::<Split>(entity));
+    save(boxed::<Spaceship>(entity));
+    save(boxed::<SpaceshipState>(entity));
+    save(boxed::<GunTimer>(entity));
+    save(boxed::<Weapon>(entity));
+    save(boxed::<Loadout>(entity));
+    save(boxed::<FlightModel>(entity));
+    save(boxed::<ControlMode>(entity));
+    save(boxed::<PlayerSlot>(entity));
+    save(boxed::<CameraData>(entity));
+    save(boxed::<OrthographicProjection>(entity));
+
+    save(boxed::<Sprite>(entity));
+    save(boxed::<Handle<Image>>(entity));
+    save(boxed::<Transform>(entity));
+    save(boxed::<GlobalTransform>(entity));
+    save(boxed::<Visibility>(entity));
+    save(boxed::<InheritedVisibility>(entity));
+    save(boxed::<ViewVisibility>(entity));
+    save(boxed::<Aabb>(entity));
+    components
+}
+
+#[derive(Resource)]
+struct QuickSave(WorldSnapshot);
+
+fn quick_save_and_load(world: &mut World) {
+    let keys = world.resource::<ButtonInput<KeyCode>>();
+    let (save, load) = (keys.just_pressed(KeyCode::F5), keys.just_pressed(KeyCode::F9));
+    if save {
+        let snapshot = WorldSnapshot::take(world);
+        world.insert_resource(QuickSave(snapshot));
+        info!("snapshot taken");
+    }
+    if load && world.contains_resource::<QuickSave>() {
+        world.resource_scope(|world, mut quick_save: Mut<QuickSave>| quick_save.0.restore(world));
+        info!("rewound to snapshot");
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::time::Duration;
+    use bevy::time::TimeUpdateStrategy;
+    use rand::{RngCore, SeedableRng};
+    use crate::bullet::BulletPlugin;
+    use crate::camera::CameraPlugin;
+    use crate::enemy::EnemiesPlugin;
+    use crate::explosion::ExplosionEvent;
+    use crate::input::{ActionButton, InputAction, InputState};
+    use crate::physics::PhysicsPlugin;
+    use crate::spaceship::SpaceshipPlugin;
+    use super::*;
+
+    /// A ship firing into the enemy formation while drifting sideways on boost, which zooms out
+    /// and shakes the camera.
+    fn app() -> App {
+        let mut app = App::new();
+        app.add_plugins((
+            MinimalPlugins,
+            AssetPlugin::default(),
+            PhysicsPlugin::default(),
+            SpaceshipPlugin { players: 1, control_mode: ControlMode::Translate },
+            EnemiesPlugin,
+            BulletPlugin::default(),
+            CameraPlugin,
+        ))
+            .init_asset::<Image>()
+            .add_event::<ExplosionEvent>();
+        app.world.spawn(GameRng(ChaCha8Rng::seed_from_u64(3)));
+
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.update();
+        let ship = app.world.query_filtered::<Entity, With<Spaceship>>().single(&app.world);
+        let mut input_state = InputState { shooting: true, boost: true, ..default() };
+        input_state.set_movement(Vec2::new(0.3, 0.));
+        app.world.entity_mut(ship).insert(input_state);
+        let timestep = app.world.resource::<Time<Fixed>>().timestep();
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(timestep));
+        app
+    }
+
+    fn run(app: &mut App, ticks: u32) {
+        for _ in 0..ticks {
+            app.update();
+        }
+    }
+
+    /// Bit patterns of everything simulated, sorted so respawned entities compare equal.
+    fn fingerprint(app: &mut App) -> (Vec<[u32; 9]>, [u32; 3], u64) {
+        let mut bodies: Vec<[u32; 9]> = app.world
+            .query::<(&Position, &Velocity, Option<&Health>, Option<&BulletTimer>, Option<&GunTimer>)>()
+            .iter(&app.world)
+            .map(|(position, velocity, health, bullet_timer, gun_timer)| [
+                position.current.x.to_bits(),
+                position.current.y.to_bits(),
+                position.previous.x.to_bits(),
+                position.previous.y.to_bits(),
+                velocity.0.x.to_bits(),
+                velocity.0.y.to_bits(),
+                health.map_or(0, |health| health.0.to_bits()),
+                bullet_timer.map_or(0, |timer| timer.0.elapsed().as_nanos() as u32),
+                gun_timer.map_or(0, |timer| timer.0.elapsed().as_nanos() as u32),
+            ])
+            .collect();
+        bodies.sort_unstable();
+        let (camera, projection) = app.world
+            .query_filtered::<(&Position, &OrthographicProjection), With<Camera>>()
+            .single(&app.world);
+        let camera = [camera.current.x.to_bits(), camera.current.y.to_bits(), projection.scale.to_bits()];
+        let next_roll = app.world.query::<&mut GameRng>().single_mut(&mut app.world).0.clone().next_u64();
+        (bodies, camera, next_roll)
+    }
+
+    fn enemies(app: &mut App) -> usize {
+        app.world.query_filtered::<(), With<Enemy>>().iter(&app.world).count()
+    }
+
+    #[test]
+    fn restore_then_resimulate_is_bit_identical() {
+        let mut app = app();
+        run(&mut app, 40);
+        let mut snapshot = WorldSnapshot::take(&mut app.world);
+        let enemies_at_snapshot = enemies(&mut app);
+
+        run(&mut app, 60);
+        let first = fingerprint(&mut app);
+        assert!(enemies(&mut app) < enemies_at_snapshot, "nothing was destroyed, the test proves little");
+
+        snapshot.restore(&mut app.world);
+        assert_eq!(enemies(&mut app), enemies_at_snapshot);
+        run(&mut app, 60);
+        assert_eq!(first, fingerprint(&mut app));
+
+        // and again, now that the destroyed enemies have been respawned once
+        snapshot.restore(&mut app.world);
+        run(&mut app, 60);
+        assert_eq!(first, fingerprint(&mut app));
+
+        // pooled bolts refired as lance bolts and missiles pick up piercing and homing
+        snapshot.restore(&mut app.world);
+        for _ in 0..3 {
+            switch_weapon(&mut app);
+            run(&mut app, 30);
+        }
+        let homing = app.world.query::<(&Homing, &Simulated)>().iter(&app.world).filter(|(_, simulated)| simulated.0).count();
+        assert!(homing > 0, "no missiles were fired, the test proves little");
+        snapshot.restore(&mut app.world);
+        run(&mut app, 60);
+        assert_eq!(first, fingerprint(&mut app));
+    }
+
+    /// Presses the weapon switch for one tick.
+    fn switch_weapon(app: &mut App) {
+        let set_pressed = |app: &mut App, pressed: bool| {
+            let mut input_state = app.world.query_filtered::<&mut InputState, With<Spaceship>>().single_mut(&mut app.world);
+            input_state.actions.insert(InputAction::SwitchWeapon, ActionButton { pressed, just_pressed: pressed, ..default() });
+        };
+        set_pressed(app, true);
+        app.update();
+        set_pressed(app, false);
+    }
+}
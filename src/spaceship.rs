@@ -1,39 +1,113 @@
-use crate::bullet::{Bullet, BulletBundle, BulletTimer};
+use crate::beam::BeamSprite;
+use crate::bullet::{insert_or_remove, Bullet, BulletBundle, BulletTimer, ProjectilePool, PLAYER_BULLET_LAYERS};
+use crate::collider::{Collider, CollisionLayers, ContinuousCollision, ConvexPolygon, Layers};
 use crate::enemy::setup_enemies;
-use crate::input::InputState;
-use crate::physics::{Position, Velocity};
+use crate::flight::FlightModel;
+use crate::input::{InputAction, InputState, PlayerSlot};
+use crate::enemy::{Damage, DamageSet};
+use crate::physics::{
+    AngularVelocity, ContactEvent, Force, Mass, PhysicsSet, Position, RigidBody, Rotation, Simulated, Velocity
+};
 use bevy::math::Vec2;
 use bevy::prelude::*;
-use rand::Rng;
+use bevy::sprite::Anchor;
+use crate::replay::GameRng;
+use crate::weapon::{Loadout, Weapon};
 
-const ACCELERATION: f32 = 2500.;
-const MAX_VELOCITY: f32 = 400.;
+/// Damage dealt per unit of impulse when a ship rams something.
+const RAM_DAMAGE: f32 = 0.2;
 
-const ZERO_VELOCITY: f32 = 0.;
+const PLAYER_SPACING: f32 = 64.;
 
-const IDLE_BREAK_SPEED: f32 = 200.;
+/// How fast a ship turns in rotate-and-thrust mode, in radians per second.
+const TURN_RATE: f32 = 4.5;
 
-pub struct SpaceshipPlugin;
+/// Sprite tint per player slot, so co-op ships can be told apart.
+const PLAYER_COLORS: [Color; 4] = [
+    Color::WHITE,
+    Color::rgb(0.6, 0.8, 1.),
+    Color::rgb(1., 0.65, 0.65),
+    Color::rgb(0.65, 1., 0.65),
+];
+
+pub struct SpaceshipPlugin {
+    pub players: usize,
+    pub control_mode: ControlMode,
+}
 
 impl Plugin for SpaceshipPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_spaceship.before(setup_enemies))
+        app.add_systems(Startup, spawn_spaceships(self.players, self.control_mode).before(setup_enemies))
             .add_systems(
                 FixedUpdate,
                 (
-                    handle_spaceship_movement,
-                    velocity_guard.after(handle_spaceship_movement),
+                    (toggle_flight_assist, switch_weapon, handle_spaceship_movement).chain().before(PhysicsSet),
+                    handle_ramming.after(PhysicsSet).before(DamageSet),
                 ),
             );
     }
 }
 
-fn spawn_spaceship(mut commands: Commands, asset_server: Res<AssetServer>) {
+/// How a ship's directional input moves it.
+#[derive(Component, Clone, Copy, Debug, Default, PartialEq)]
+pub enum ControlMode {
+    /// Up, down, left and right push the ship that way; it always faces up.
+    #[default]
+    Translate,
+    /// Left and right turn the ship, up thrusts along its nose and down thrusts backwards.
+    /// Guns fire where the nose points.
+    RotateThrust,
+}
+
+impl ControlMode {
+    /// Reads `--rotate-thrust` from the command line.
+    pub fn from_args() -> Self {
+        if std::env::args().skip(1).any(|arg| arg == "--rotate-thrust") {
+            ControlMode::RotateThrust
+        } else {
+            ControlMode::Translate
+        }
+    }
+
+    /// Thrust direction in world space and turn rate for the given input and facing.
+    fn steer(self, movement: Vec2, facing: Vec2) -> (Vec2, f32) {
+        match self {
+            ControlMode::Translate => (movement, 0.),
+            // pushing right turns clockwise
+            ControlMode::RotateThrust => (facing * movement.y, -movement.x * TURN_RATE),
+        }
+    }
+}
+
+/// Spawns one ship per player, side by side around the origin.
+fn spawn_spaceships(players: usize, control_mode: ControlMode) -> impl FnMut(Commands, Res<AssetServer>) {
+    move |mut commands, asset_server| {
+        let loadout = Loadout::load("assets/weapons", &asset_server);
+        for slot in 0..players {
+            let x = (slot as f32 - (players - 1) as f32 / 2.) * PLAYER_SPACING;
+            spawn_spaceship(&mut commands, &asset_server, PlayerSlot(slot), control_mode, &loadout, Vec2::new(x, 0.));
+        }
+    }
+}
+
+fn spawn_spaceship(
+    commands: &mut Commands,
+    asset_server: &AssetServer,
+    slot: PlayerSlot,
+    control_mode: ControlMode,
+    loadout: &Loadout,
+    spawn_pos: Vec2
+) {
+    let weapon = loadout.0[0].clone();
     commands
         .spawn(SpaceshipBundle {
             sprite: SpriteBundle {
                 texture: asset_server.load("spaceship.png"),
-                transform: Transform::from_xyz(100., 0., 1.),
+                transform: Transform::from_xyz(spawn_pos.x, spawn_pos.y, 1.),
+                sprite: Sprite {
+                    color: PLAYER_COLORS[slot.0 % PLAYER_COLORS.len()],
+                    ..default()
+                },
                 ..Default::default()
             },
             state: SpaceshipState {
@@ -41,13 +115,42 @@ fn spawn_spaceship(mut commands: Commands, asset_server: Res<AssetServer>) {
             },
             velocity: Velocity(Vec2::ZERO),
             position: Position {
-                current: Vec2::ZERO,
-                previous: Vec2::ZERO,
+                current: spawn_pos,
+                previous: spawn_pos,
             },
-            marker: Spaceship { shot_ready: true },
-            gun_timer: GunTimer(Timer::from_seconds(0.1, TimerMode::Repeating)),
+            rotation: Rotation::default(),
+            angular_velocity: AngularVelocity::default(),
+            mass: Mass::new(1.),
+            body: RigidBody { restitution: 0.5 },
+            thrust: Force::default(),
+            flight_model: FlightModel::load("assets/flight.ron"),
+            control_mode,
+            marker: Spaceship,
+            gun_timer: GunTimer(Timer::new(weapon.cooldown(), TimerMode::Repeating)),
+            weapon,
+            loadout: loadout.clone(),
+            slot,
+            collider: Collider::Polygon(ConvexPolygon::new([
+                Vec2::new(0., 16.),
+                Vec2::new(15., -12.),
+                Vec2::new(-15., -12.),
+            ]).expect("the hull is a triangle")),
+            layers: CollisionLayers::new(
+                Layers::PLAYER,
+                Layers::ENEMY | Layers::ENEMY_PROJECTILE | Layers::PICKUP | Layers::HAZARD,
+            ),
         })
         .with_children(|parent| {
+            // under the ship, stretched to length while a beam weapon fires
+            parent.spawn((
+                SpriteBundle {
+                    sprite: Sprite { anchor: Anchor::BottomCenter, ..default() },
+                    visibility: Visibility::Hidden,
+                    transform: Transform::from_xyz(0., 0., -0.5),
+                    ..Default::default()
+                },
+                BeamSprite,
+            ));
             parent.spawn(FireBundle {
                 sprite: SpriteBundle {
                     texture: asset_server.load("fire2.png"),
@@ -69,181 +172,181 @@ fn spawn_spaceship(mut commands: Commands, asset_server: Res<AssetServer>) {
         });
 }
 
-fn velocity_guard(
-    mut query: Query<&mut Velocity, With<Spaceship>>,
-    input_state: Query<&InputState>,
-    time: Res<Time>,
-) {
-    let mut velocity = query.single_mut();
-    let input_state = input_state.single();
-    let is_idle = input_state.idle;
-    if velocity.0.y > MAX_VELOCITY {
-        velocity.0.y = MAX_VELOCITY
-    };
-    if velocity.0.y < -MAX_VELOCITY {
-        velocity.0.y = -MAX_VELOCITY
-    };
-    if velocity.0.x > MAX_VELOCITY {
-        velocity.0.x = MAX_VELOCITY
-    };
-    if velocity.0.x < -MAX_VELOCITY {
-        velocity.0.x = -MAX_VELOCITY
-    };
-
-    // TODO split idle to idle_x and idle_y to fix movement bugs
-    if is_idle {
-        let mut new_velocity = velocity.0;
-        // x axis
-        if new_velocity.x < 0. {
-            new_velocity.x += IDLE_BREAK_SPEED * time.delta_seconds();
-            if new_velocity.x > 0. {
-                new_velocity.x = 0.
-            }
-        } else if new_velocity.x > 0. {
-            new_velocity.x -= IDLE_BREAK_SPEED * time.delta_seconds();
-            if new_velocity.x < 0. {
-                new_velocity.x = 0.
-            }
-        }
-        // y axis
-        if new_velocity.y < ZERO_VELOCITY {
-            new_velocity.y += IDLE_BREAK_SPEED * time.delta_seconds();
-            if new_velocity.y > ZERO_VELOCITY {
-                new_velocity.y = ZERO_VELOCITY
-            }
-        } else if new_velocity.y > ZERO_VELOCITY {
-            new_velocity.y -= IDLE_BREAK_SPEED * time.delta_seconds();
-            if new_velocity.y < ZERO_VELOCITY {
-                new_velocity.y = ZERO_VELOCITY
-            }
-        }
-
-        if velocity.0 != new_velocity {
-            velocity.0 = new_velocity
-        };
-    }
-}
-
 fn handle_spaceship_movement(
     time: Res<Time>,
     mut q_spaceship: Query<
         (
-            &mut Position,
-            &mut Velocity,
+            &Position,
+            &Velocity,
+            &Rotation,
+            &mut AngularVelocity,
+            &Mass,
+            &mut Force,
+            &FlightModel,
+            &ControlMode,
+            &Weapon,
             &mut GunTimer,
             &mut SpaceshipState,
+            &InputState,
+            &Children,
         ),
         With<Spaceship>,
     >,
     mut q_fire: Query<&mut Visibility, (With<Fire>, Without<Spaceship>, Without<BoostFire>)>,
     mut q_boost_fire: Query<&mut Visibility, (With<BoostFire>, Without<Spaceship>, Without<Fire>)>,
     mut commands: Commands,
-    asset_server: Res<AssetServer>,
-    input_state: Query<&InputState>,
+    mut q_pool: Query<&mut ProjectilePool>,
+    mut rng: Query<&mut GameRng>,
 ) {
-    let (mut position, mut velocity, mut gun_timer, mut spaceship_state) = q_spaceship.single_mut();
-    let mut vis_fire = q_fire.single_mut();
-    let mut vis_boost_fire = q_boost_fire.single_mut();
-    let input_state = input_state.single();
+    let mut rng = rng.single_mut();
+    let mut pool = q_pool.single_mut();
+    for (position, velocity, rotation, mut angular_velocity, mass, mut thrust, flight_model, control_mode, weapon, mut gun_timer, mut spaceship_state, input_state, children) in q_spaceship.iter_mut() {
+        for &child in children.iter() {
+            if let Ok(mut vis_fire) = q_fire.get_mut(child) {
+                *vis_fire = if input_state.up && !input_state.boost {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                };
+            }
+            if let Ok(mut vis_boost_fire) = q_boost_fire.get_mut(child) {
+                *vis_boost_fire = if input_state.up && input_state.boost {
+                    Visibility::Visible
+                } else {
+                    Visibility::Hidden
+                };
+            }
+        }
 
-    *vis_fire = if input_state.up && !input_state.boost {
-        Visibility::Visible
-    } else {
-        Visibility::Hidden
-    };
-    *vis_boost_fire = if input_state.up && input_state.boost {
-        Visibility::Visible
-    } else {
-        Visibility::Hidden
-    };
+        let facing = Vec2::from_angle(rotation.current).rotate(Vec2::Y);
+        let (movement, turn_rate) = control_mode.steer(input_state.movement, facing);
+        angular_velocity.0 = turn_rate;
+        thrust.0 += flight_model.acceleration(velocity.0, movement, facing, time.delta_seconds()) * mass.get();
+        // beams fire continuously, see the beam module
+        if input_state.shooting && weapon.beam.is_none() {
+            handle_fire(
+                &mut commands,
+                &mut gun_timer,
+                &mut pool,
+                weapon,
+                position,
+                velocity,
+                &mut spaceship_state,
+                &mut rng,
+                input_state.aim,
+                facing,
+            );
+        }
 
-    if input_state.up {
-        velocity.0.y += ACCELERATION * time.delta_seconds();
-    }
-    if input_state.down {
-        velocity.0.y -= ACCELERATION * time.delta_seconds();
-    }
-    if input_state.right {
-        velocity.0.x += ACCELERATION * time.delta_seconds();
-    }
-    if input_state.left {
-        velocity.0.x -= ACCELERATION * time.delta_seconds();
+        if gun_timer.0.tick(time.delta()).finished() {
+            spaceship_state.shot_ready = true;
+        }
     }
-    if input_state.shooting {
-        handle_fire(
-            &mut commands,
-            &mut gun_timer,
-            &asset_server,
-            &mut position,
-            &mut velocity,
-            &mut spaceship_state,
-        );
+}
+
+fn toggle_flight_assist(mut q_spaceship: Query<(&mut FlightModel, &InputState), With<Spaceship>>) {
+    for (mut flight_model, input_state) in q_spaceship.iter_mut() {
+        if input_state.action(InputAction::FlightAssist).just_pressed {
+            flight_model.assist = !flight_model.assist;
+        }
     }
+}
 
-    if gun_timer.0.tick(time.delta()).finished() {
-        spaceship_state.shot_ready = true;
+/// Whatever a ship bumps into takes damage, the harder the bump the more.
+fn handle_ramming(
+    mut contacts: EventReader<ContactEvent>,
+    mut e_damage: EventWriter<Damage>,
+    q_spaceship: Query<(), With<Spaceship>>
+) {
+    for contact in contacts.read() {
+        let damage = contact.impulse.length() * RAM_DAMAGE;
+        if q_spaceship.contains(contact.a) {
+            e_damage.send(Damage(contact.b, damage));
+        }
+        if q_spaceship.contains(contact.b) {
+            e_damage.send(Damage(contact.a, damage));
+        }
     }
 }
 
 fn handle_fire(
-    mut commands: &mut Commands,
-    mut gun_timer: &mut GunTimer,
-    asset_server: &AssetServer,
-    mut position: &mut Position,
-    mut velocity: &mut Velocity,
-    mut spaceship_state: &mut SpaceshipState,
+    commands: &mut Commands,
+    gun_timer: &mut GunTimer,
+    pool: &mut ProjectilePool,
+    weapon: &Weapon,
+    position: &Position,
+    velocity: &Velocity,
+    spaceship_state: &mut SpaceshipState,
+    rng: &mut GameRng,
+    aim: Option<Vec2>,
+    facing: Vec2,
 ) {
     if spaceship_state.shot_ready {
+        gun_timer.0.set_duration(weapon.cooldown());
         gun_timer.0.reset();
-        let x_right = rand::thread_rng().gen_range(-40.0..40.);
-        let x_left = rand::thread_rng().gen_range(-40.0..40.);
-        let bullet_velocity = 400. + if velocity.0.y > 0. { velocity.0.y } else { 0. };
-        commands.spawn(BulletBundle {
-            sprite: SpriteBundle {
-                texture: asset_server.load("bullet.png"),
-                ..Default::default()
-            },
-            position: Position {
-                current: Vec2::new(position.current.x + 10., position.current.y + 8.),
-                previous: Vec2::new(position.current.x + 10., position.current.y + 8.),
-            },
-            velocity: Velocity(Vec2::new(x_right, bullet_velocity)),
-            marker: Bullet,
-            timer: BulletTimer(Timer::from_seconds(10.5, TimerMode::Once)),
-        });
-        commands.spawn(BulletBundle {
-            sprite: SpriteBundle {
-                texture: asset_server.load("bullet.png"),
-                ..Default::default()
-            },
-            velocity: Velocity(Vec2::new(x_left, bullet_velocity)),
-            position: Position {
-                current: Vec2::new(position.current.x - 10., position.current.y + 8.),
-                previous: Vec2::new(position.current.x - 10., position.current.y + 8.),
-            },
-            marker: Bullet,
-            timer: BulletTimer(Timer::from_seconds(10.5, TimerMode::Once)),
-        });
+        let forward = firing_direction(position.current, aim, facing);
+        for shot in weapon.shots(forward, velocity.0.dot(forward), &mut rng.0) {
+            let shot_position = position.current + shot.offset;
+            let mut bullet = pool.fire(commands, BulletBundle {
+                sprite: SpriteBundle {
+                    texture: weapon.texture.clone(),
+                    ..Default::default()
+                },
+                rotation: Rotation::new(Vec2::Y.angle_between(shot.direction)),
+                position: Position {
+                    current: shot_position,
+                    previous: shot_position,
+                },
+                velocity: Velocity(shot.velocity),
+                marker: Bullet::new(weapon.damage),
+                timer: BulletTimer(Timer::from_seconds(weapon.lifetime, TimerMode::Once)),
+                collider: weapon.collider.clone(),
+                layers: PLAYER_BULLET_LAYERS,
+                continuous: ContinuousCollision,
+                simulated: Simulated(true),
+            });
+            // a reused projectile may have been fired by another weapon before
+            insert_or_remove(&mut bullet, weapon.homing.clone());
+            insert_or_remove(&mut bullet, weapon.blast);
+            insert_or_remove(&mut bullet, weapon.pierce);
+            insert_or_remove(&mut bullet, weapon.ricochet);
+            insert_or_remove(&mut bullet, weapon.split);
+        }
         spaceship_state.shot_ready = false;
     }
 }
 
-#[derive(Component)]
-pub struct Spaceship {
-    shot_ready: bool,
+/// Where a ship at `position` fires: along the nose unless twin-stick aiming points elsewhere.
+pub fn firing_direction(position: Vec2, aim: Option<Vec2>, facing: Vec2) -> Vec2 {
+    aim
+        .map(|target| (target - position).normalize_or_zero())
+        .filter(|direction| *direction != Vec2::ZERO)
+        .unwrap_or(facing)
 }
 
-#[derive(Component)]
-pub struct SpaceshipState {
-    shot_ready: bool
+/// Cycles through the ship's loadout.
+fn switch_weapon(mut q_spaceship: Query<(&mut Weapon, &Loadout, &InputState), With<Spaceship>>) {
+    for (mut weapon, loadout, input_state) in q_spaceship.iter_mut() {
+        if input_state.action(InputAction::SwitchWeapon).just_pressed {
+            if let Some(next) = loadout.next(&weapon) {
+                *weapon = next.clone();
+            }
+        }
+    }
 }
 
-struct Movement {
-    up: bool,
-    right: bool,
-    left: bool,
-    down: bool,
-    idle: bool,
+/// Average current position, or `None` when there are no positions.
+pub fn centroid<'a>(positions: impl Iterator<Item = &'a Position>) -> Option<Vec2> {
+    let (sum, count) = positions.fold((Vec2::ZERO, 0), |(sum, count), position| (sum + position.current, count + 1));
+    (count > 0).then(|| sum / count as f32)
+}
+
+#[derive(Component, Clone)]
+pub struct Spaceship;
+
+#[derive(Component, Clone)]
+pub struct SpaceshipState {
+    shot_ready: bool
 }
 
 #[derive(Component)]
@@ -252,17 +355,29 @@ pub struct Fire;
 #[derive(Component)]
 pub struct BoostFire;
 
-#[derive(Component)]
-pub struct GunTimer(Timer);
+#[derive(Component, Clone)]
+pub struct GunTimer(pub Timer);
 
 #[derive(Bundle)]
 pub struct SpaceshipBundle {
     pub sprite: SpriteBundle,
     pub velocity: Velocity,
     pub position: Position,
+    pub rotation: Rotation,
+    pub angular_velocity: AngularVelocity,
+    pub mass: Mass,
+    pub body: RigidBody,
+    pub thrust: Force,
+    pub flight_model: FlightModel,
+    pub control_mode: ControlMode,
     pub marker: Spaceship,
     pub gun_timer: GunTimer,
+    pub weapon: Weapon,
+    pub loadout: Loadout,
     pub state: SpaceshipState,
+    pub slot: PlayerSlot,
+    pub collider: Collider,
+    pub layers: CollisionLayers,
 }
 
 #[derive(Bundle)]
@@ -277,5 +392,20 @@ pub struct BoostFireBundle {
     pub marker: BoostFire,
 }
 
-#[derive(SystemSet, Debug, Clone, PartialEq, Eq, Hash)]
-pub struct SpaceshipStartupSet;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rotate_thrust_turns_on_left_right_and_thrusts_along_the_nose() {
+        let facing = Vec2::X;
+        let (thrust, turn_rate) = ControlMode::RotateThrust.steer(Vec2::new(1., 1.), facing);
+        assert_eq!(thrust, facing);
+        assert!(turn_rate < 0., "right should turn clockwise");
+        let (thrust, turn_rate) = ControlMode::RotateThrust.steer(Vec2::new(-1., 0.), facing);
+        assert_eq!(thrust, Vec2::ZERO);
+        assert!(turn_rate > 0.);
+
+        assert_eq!(ControlMode::Translate.steer(Vec2::new(1., 1.), facing), (Vec2::new(1., 1.), 0.));
+    }
+}
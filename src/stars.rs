@@ -1,10 +1,12 @@
 use std::collections::HashMap;
+use std::collections::hash_map::Entry;
 use std::hash::{Hash, Hasher};
 use bevy::prelude::*;
 use crate::camera::VisibleSpace;
+use crate::origin::FloatingOrigin;
 use std::collections::hash_map::DefaultHasher;
 
-const STARS_DENSITY: f32 = 30.;
+pub const STARS_DENSITY: f32 = 30.;
 const VISIBLE_SPACE_MARGINS: f32 = STARS_DENSITY * 2.;
 
 pub struct StarsPlugin;
@@ -12,17 +14,18 @@ pub struct StarsPlugin;
 #[derive(Component)]
 pub struct Star;
 
+/// Grid cell of a star in absolute coordinates, which stay put when the floating origin moves.
 #[derive(Component)]
-pub struct StarKey((i32, i32));
+pub struct StarKey(pub (i64, i64));
 
 #[derive(Component, Debug)]
 pub struct VisibleStarField {
-    top_left: Vec2,
-    bottom_right: Vec2
+    pub top_left: Vec2,
+    pub bottom_right: Vec2
 }
 
 #[derive(Component)]
-struct StarMap(HashMap<(i32, i32), bool>);
+struct StarMap(HashMap<(i64, i64), bool>);
 
 impl Plugin for StarsPlugin {
     fn build(&self, app: &mut App) {
@@ -76,18 +79,20 @@ fn handle_star_spawning(
     mut commands: Commands,
     asset_server: Res<AssetServer>,
     q_visible_starfield: Query<&VisibleStarField>,
+    q_origin: Query<&FloatingOrigin>,
     mut q_starmap: Query<&mut StarMap>
 ) {
     let mut starmap = q_starmap.single_mut();
+    let origin = q_origin.single().0;
     let VisibleStarField {
         top_left,
         bottom_right
     } = q_visible_starfield.single();
     let mut v_index = *top_left;
     loop {
-        let star_key = (v_index.x as i32, v_index.y as i32);
-        if starmap.0.get(&star_key).is_none() {
-                starmap.0.insert(star_key, true);
+        let star_key = (v_index.x as i64 + origin.x, v_index.y as i64 + origin.y);
+        if let Entry::Vacant(entry) = starmap.0.entry(star_key) {
+                entry.insert(true);
                 let (is_star, x_offset, y_offset, scale) = generate_star_properties(star_key, STARS_DENSITY, 2.);
                 if !is_star { continue };
                 commands.spawn((
@@ -139,7 +144,7 @@ fn hash<T: Hash>(t: &T) -> u64 {
     s.finish()
 }
 
-fn generate_star_properties(key: (i32, i32), max_offset: f32, max_scale: f32) -> (bool, f32, f32, f32) {
+fn generate_star_properties(key: (i64, i64), max_offset: f32, max_scale: f32) -> (bool, f32, f32, f32) {
     let hash = hash(&key);
     let normalized_value = (hash % 1000000) as f32 / 1000000.0;
     let is_star_threshold = 0.1;
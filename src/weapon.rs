@@ -0,0 +1,227 @@
+use std::fs;
+use std::time::Duration;
+use bevy::prelude::*;
+use rand::Rng;
+use serde::Deserialize;
+use crate::beam::Beam;
+use crate::bullet::{Pierce, Ricochet, Split};
+use crate::collider::Collider;
+use crate::missile::{Blast, Homing};
+
+/// How far ahead of the ship's centre projectiles appear.
+pub const MUZZLE_OFFSET: f32 = 8.;
+
+/// What a ship fires. Read from the RON files in `assets/weapons`; swap it to change guns.
+#[derive(Component, Clone, Debug, Deserialize)]
+#[serde(default)]
+pub struct Weapon {
+    pub name: String,
+    /// Projectiles per shot.
+    pub projectiles: u32,
+    pub spread: Spread,
+    /// Muzzle speed, on top of the ship's own forward speed.
+    pub speed: f32,
+    /// Damage per projectile, or per second for a beam.
+    pub damage: f32,
+    /// Shots per second.
+    pub fire_rate: f32,
+    /// Seconds before a projectile that hit nothing disappears, unless it leaves the screen first.
+    pub lifetime: f32,
+    pub sprite: String,
+    /// `sprite`, loaded once when the loadout is.
+    #[serde(skip)]
+    pub texture: Handle<Image>,
+    pub collider: Collider,
+    /// Makes the projectiles missiles that chase enemies.
+    pub homing: Option<Homing>,
+    /// Makes the projectiles explode on impact.
+    pub blast: Option<Blast>,
+    pub pierce: Option<Pierce>,
+    pub ricochet: Option<Ricochet>,
+    pub split: Option<Split>,
+    /// Makes the weapon a beam that hits instantly for as long as the trigger is held, instead
+    /// of firing projectiles.
+    pub beam: Option<Beam>,
+}
+
+impl Default for Weapon {
+    fn default() -> Self {
+        Weapon {
+            name: "Blaster".to_string(),
+            projectiles: 2,
+            spread: Spread::Parallel { spacing: 20., jitter: 40. },
+            speed: 400.,
+            damage: 100.,
+            fire_rate: 10.,
+            lifetime: 10.5,
+            sprite: "bullet.png".to_string(),
+            texture: Handle::default(),
+            // laser bolts are long and thin, and turn with the direction they were fired in
+            collider: Collider::Capsule(Capsule2d::new(1., 14.)),
+            homing: None,
+            blast: None,
+            pierce: None,
+            ricochet: None,
+            split: None,
+            beam: None,
+        }
+    }
+}
+
+/// How the projectiles of one shot are laid out.
+#[derive(Clone, Copy, Debug, Deserialize)]
+pub enum Spread {
+    /// Side by side, `spacing` apart, each drifting sideways at a random speed of up to `jitter`.
+    Parallel { spacing: f32, jitter: f32 },
+    /// Fanned out evenly over `angle` radians.
+    Fan { angle: f32 },
+}
+
+/// One projectile leaving the muzzle.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Shot {
+    /// From the ship's position, in world space.
+    pub offset: Vec2,
+    /// The way the projectile points, before any sideways jitter.
+    pub direction: Vec2,
+    pub velocity: Vec2,
+}
+
+impl Weapon {
+    /// Reads a weapon from a RON file. Settings left out keep their default.
+    pub fn load(path: &str) -> Option<Self> {
+        let contents = fs::read_to_string(path)
+            .map_err(|err| warn!("could not read {path} ({err}), skipping this weapon"))
+            .ok()?;
+        ron::from_str(&contents)
+            .map_err(|err| warn!("could not parse {path} ({err}), skipping this weapon"))
+            .ok()
+    }
+
+    pub fn cooldown(&self) -> Duration {
+        Duration::from_secs_f32(1. / self.fire_rate.max(f32::EPSILON))
+    }
+
+    /// The projectiles of one shot fired along `forward` by a ship moving at `forward_speed`
+    /// along it. Ships moving backwards don't slow their shots down.
+    pub fn shots(&self, forward: Vec2, forward_speed: f32, rng: &mut impl Rng) -> Vec<Shot> {
+        let right = Vec2::new(forward.y, -forward.x);
+        let speed = self.speed + forward_speed.max(0.);
+        // centred on the nose, from right to left
+        let count = self.projectiles.max(1);
+        let slots = (0..count).map(|i| (count - 1) as f32 / 2. - i as f32);
+        match self.spread {
+            Spread::Parallel { spacing, jitter } => slots
+                .map(|slot| {
+                    let drift = if jitter > 0. { rng.gen_range(-jitter..jitter) } else { 0. };
+                    Shot {
+                        offset: right * slot * spacing + forward * MUZZLE_OFFSET,
+                        direction: forward,
+                        velocity: forward * speed + right * drift,
+                    }
+                })
+                .collect(),
+            Spread::Fan { angle } => fan(forward, count, angle)
+                .map(|direction| Shot { offset: forward * MUZZLE_OFFSET, direction, velocity: direction * speed })
+                .collect(),
+        }
+    }
+}
+
+/// `count` directions spread evenly over `angle` radians around `forward`, from right to left.
+pub fn fan(forward: Vec2, count: u32, angle: f32) -> impl Iterator<Item = Vec2> {
+    let step = if count > 1 { angle / (count - 1) as f32 } else { 0. };
+    // clockwise is to the right
+    (0..count).map(move |i| Vec2::from_angle((i as f32 - (count - 1) as f32 / 2.) * step).rotate(forward))
+}
+
+/// Weapons a ship can switch between, in the order they are cycled through.
+#[derive(Component, Clone, Debug)]
+pub struct Loadout(pub Vec<Weapon>);
+
+impl Loadout {
+    /// Every weapon file in `dir`, in file name order, with their sprites. Falls back to the
+    /// default weapon when there are none.
+    pub fn load(dir: &str, asset_server: &AssetServer) -> Self {
+        let mut loadout = Self::read(dir);
+        for weapon in &mut loadout.0 {
+            weapon.texture = asset_server.load(weapon.sprite.clone());
+        }
+        loadout
+    }
+
+    fn read(dir: &str) -> Self {
+        let mut paths: Vec<String> = match fs::read_dir(dir) {
+            Ok(entries) => entries
+                .filter_map(|entry| entry.ok())
+                .map(|entry| entry.path())
+                .filter(|path| path.extension().is_some_and(|extension| extension == "ron"))
+                .map(|path| path.to_string_lossy().into_owned())
+                .collect(),
+            Err(err) => {
+                warn!("could not read {dir} ({err})");
+                Vec::new()
+            }
+        };
+        paths.sort();
+        let weapons: Vec<Weapon> = paths.iter().filter_map(|path| Weapon::load(path)).collect();
+        if weapons.is_empty() {
+            warn!("no weapons in {dir}, using the default weapon");
+            return Loadout(vec![Weapon::default()]);
+        }
+        Loadout(weapons)
+    }
+
+    /// The weapon after `current`, wrapping around.
+    pub fn next(&self, current: &Weapon) -> Option<&Weapon> {
+        let index = self.0.iter().position(|weapon| weapon.name == current.name).map_or(0, |index| index + 1);
+        self.0.get(index % self.0.len().max(1))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use super::*;
+
+    #[test]
+    fn shipped_weapons_parse() {
+        let loadout = Loadout::read("assets/weapons");
+        assert!(loadout.0.len() > 1);
+        for entry in fs::read_dir("assets/weapons").unwrap() {
+            let contents = fs::read_to_string(entry.unwrap().path()).unwrap();
+            assert!(ron::from_str::<Weapon>(&contents).is_ok());
+        }
+    }
+
+    #[test]
+    fn parallel_shots_sit_side_by_side_and_inherit_forward_speed() {
+        let weapon = Weapon { spread: Spread::Parallel { spacing: 20., jitter: 0. }, ..default() };
+        let shots = weapon.shots(Vec2::Y, 100., &mut ChaCha8Rng::seed_from_u64(0));
+        assert_eq!(shots.len(), 2);
+        assert_eq!(shots[0].offset, Vec2::new(10., MUZZLE_OFFSET));
+        assert_eq!(shots[1].offset, Vec2::new(-10., MUZZLE_OFFSET));
+        assert!(shots.iter().all(|shot| shot.velocity == Vec2::new(0., 500.)));
+        // backing away doesn't slow the shots
+        assert_eq!(weapon.shots(Vec2::Y, -100., &mut ChaCha8Rng::seed_from_u64(0))[0].velocity, Vec2::new(0., 400.));
+    }
+
+    #[test]
+    fn fan_spreads_evenly_around_the_nose() {
+        let weapon = Weapon { projectiles: 3, spread: Spread::Fan { angle: 1. }, ..default() };
+        let shots = weapon.shots(Vec2::Y, 0., &mut ChaCha8Rng::seed_from_u64(0));
+        let angles: Vec<f32> = shots.iter().map(|shot| Vec2::Y.angle_between(shot.direction)).collect();
+        assert!((angles[0] + 0.5).abs() < 1e-5 && angles[1].abs() < 1e-5 && (angles[2] - 0.5).abs() < 1e-5, "{angles:?}");
+        assert!(shots.iter().all(|shot| (shot.velocity.length() - 400.).abs() < 1e-3));
+    }
+
+    #[test]
+    fn loadout_cycles_through_its_weapons() {
+        let named = |name: &str| Weapon { name: name.to_string(), ..default() };
+        let loadout = Loadout(vec![named("a"), named("b")]);
+        assert_eq!(loadout.next(&named("a")).unwrap().name, "b");
+        assert_eq!(loadout.next(&named("b")).unwrap().name, "a");
+        assert_eq!(loadout.next(&named("unknown")).unwrap().name, "a");
+    }
+}